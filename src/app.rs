@@ -1,18 +1,23 @@
 //use egui_plotter::EguiBackend;
 //use plotters::prelude::*;
+use crate::backend_talk;
 use crate::common_data;
-use crate::components::{dataframe_table, explorer, modal_window, plotter_2d};
+use crate::components::{
+    connection_settings, dataframe_table, explorer, modal_window, network_inspector, plotter_2d,
+};
+use crate::session;
+use crate::workspace;
 use eframe::egui::{self, FontData};
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct WorldPlayer {
-    selected_world_name: Option<String>,
+    selected_world: Option<common_data::WorldKey>,
 }
 
 impl Default for WorldPlayer {
     fn default() -> Self {
         Self {
-            selected_world_name: None,
+            selected_world: None,
         }
     }
 }
@@ -33,31 +38,33 @@ impl WorldPlayer {
             if ui.button("Update World List").clicked() {
                 common_data.update_world_list();
             }
-            let world_list_opt = common_data.get_world_list();
-            if let Some(world_list) = world_list_opt {
+            let world_lists = common_data.get_world_lists();
+            if !world_lists.is_empty() {
+                let selected_text = match &self.selected_world {
+                    Some(world) => world_label(common_data, world),
+                    None => "".to_string(),
+                };
                 egui::ComboBox::from_id_source("world_select")
-                    .selected_text(self.selected_world_name.clone().unwrap_or_default())
+                    .selected_text(selected_text)
                     .show_ui(ui, |ui| {
                         ui.style_mut().wrap = Some(false);
                         ui.set_min_width(60.0);
-                        for world_meta in world_list.list.iter() {
-                            let txt = world_meta.id.clone().unwrap().id;
-                            ui.selectable_value(
-                                &mut self.selected_world_name,
-                                Some(txt.clone()),
-                                txt.clone(),
-                            );
+                        for (backend, world_list) in world_lists.iter() {
+                            for world_meta in world_list.list.iter() {
+                                let world = common_data::WorldKey {
+                                    backend: *backend,
+                                    name: world_meta.id.clone().unwrap().id,
+                                };
+                                let txt = world_label(common_data, &world);
+                                ui.selectable_value(&mut self.selected_world, Some(world), txt);
+                            }
                         }
                     });
                 // show world history length and current index
-                let mut history_len = 0;
-                if let Some(selected_name) = &self.selected_world_name {
-                    for world_meta in world_list.list.iter() {
-                        if world_meta.id.clone().unwrap().id == *selected_name {
-                            history_len = world_meta.total_frame_num;
-                        }
-                    }
-                }
+                let history_len = match &self.selected_world {
+                    Some(world) => common_data.world_frame_num(world),
+                    None => 0,
+                };
                 // controller buttons
                 if ui.button("Previous Frame").clicked() {
                     common_data.world.previous();
@@ -69,6 +76,20 @@ impl WorldPlayer {
                 if ui.button("Next Frame").clicked() {
                     common_data.world.next();
                 }
+                ui.checkbox(&mut common_data.follow_latest, "follow latest");
+                ui.add(
+                    egui::DragValue::new(&mut common_data.world_prefetch_window)
+                        .clamp_range(0..=10000)
+                        .prefix("prefetch: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut common_data.world_batch_size)
+                        .clamp_range(1..=500)
+                        .prefix("batch: "),
+                );
+                // the world list is not refreshed while following, the received frames are newer
+                let history_len =
+                    std::cmp::max(history_len as usize, common_data.world.history.len());
                 // world infomation
                 ui.label(format!(
                     "current frame : {} / {}",
@@ -86,6 +107,32 @@ impl WorldPlayer {
     }
 }
 
+/// Dot colored by the health of the backend at `address`, with its state on hover.
+fn connection_state_ui(ui: &mut egui::Ui, address: &str, state: common_data::ConnectionState) {
+    let (color, text) = match state {
+        common_data::ConnectionState::Connected => (egui::Color32::GREEN, "connected"),
+        common_data::ConnectionState::Degraded => (egui::Color32::YELLOW, "degraded"),
+        common_data::ConnectionState::Unauthorized => {
+            (egui::Color32::LIGHT_RED, "credentials rejected")
+        }
+        common_data::ConnectionState::Disconnected => (egui::Color32::RED, "disconnected"),
+    };
+    ui.label(egui::RichText::new("●").color(color))
+        .on_hover_text(format!("{}: {}", address, text));
+}
+
+/// Name of a world, with its server once more than one is connected.
+fn world_label(common_data: &common_data::CommonData, world: &common_data::WorldKey) -> String {
+    if common_data.backends.len() > 1 {
+        let address = common_data
+            .server_address(world.backend)
+            .unwrap_or_default();
+        format!("{} ({})", world.name, address)
+    } else {
+        world.name.clone()
+    }
+}
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -94,6 +141,21 @@ pub struct TemplateApp {
 
     world_player: WorldPlayer,
 
+    connection_settings: connection_settings::ConnectionSettings,
+
+    // files and rosbags loaded on the backends, reloaded on the next start
+    session: session::Session,
+
+    #[serde(skip)]
+    show_connection_settings: bool,
+
+    // set when a server rejected the credentials, shown in the connection settings until reconnecting
+    #[serde(skip)]
+    credentials_prompt: Option<String>,
+
+    #[serde(skip)]
+    show_notifications: bool,
+
     #[serde(skip)]
     modal_window: modal_window::ModalWindow,
 
@@ -106,13 +168,25 @@ pub struct TemplateApp {
     #[serde(skip)]
     explorer: explorer::Explorer,
 
+    // file chosen in the browser's file dialog, not yet shown in the load dialog
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    picked_file: Option<poll_promise::Promise<Option<modal_window::ModalWindowInput>>>,
+    // dialogs run in the background, so that rendering and health checks go on meanwhile
+    #[serde(skip)]
+    picked_workspace: Option<poll_promise::Promise<Option<Result<Vec<u8>, String>>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    saved_workspace: Option<poll_promise::Promise<Result<(), String>>>,
+
     #[serde(skip)]
     common_data: std::sync::Arc<std::sync::Mutex<common_data::CommonData>>,
 }
 
 impl Default for TemplateApp {
     fn default() -> Self {
-        let common_data = common_data::CommonData::default();
+        let connection_settings = connection_settings::ConnectionSettings::default();
+        let common_data = common_data::CommonData::new(connection_settings.endpoints());
         let common_data_arc = std::sync::Arc::new(std::sync::Mutex::new(common_data));
 
         let mut next_view_nr = 0;
@@ -145,6 +219,9 @@ impl Default for TemplateApp {
         tabs.push(tiles.insert_pane(gen_view(PaneType::Plotter2D(
             plotter_2d::Plotter2D::default(),
         ))));
+        tabs.push(tiles.insert_pane(gen_view(PaneType::NetworkInspector(
+            network_inspector::NetworkInspector::default(),
+        ))));
 
         let root = tiles.insert_tab_tile(tabs);
 
@@ -153,10 +230,20 @@ impl Default for TemplateApp {
         Self {
             tree: tree,
             world_player: WorldPlayer::default(),
+            connection_settings: connection_settings,
+            session: session::Session::default(),
+            show_connection_settings: false,
+            credentials_prompt: None,
+            show_notifications: false,
             modal_window: modal_window::ModalWindow::default(),
             behavior: TreeBehavior::new(common_data_arc.clone()),
             last_tree_debug: Default::default(),
             explorer: explorer::Explorer::default(),
+            #[cfg(target_arch = "wasm32")]
+            picked_file: None,
+            picked_workspace: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            saved_workspace: None,
             common_data: common_data_arc.clone(),
         }
     }
@@ -164,7 +251,8 @@ impl Default for TemplateApp {
 
 impl TemplateApp {
     /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    /// `server_override` replaces the persisted connection profile, e.g. from the command line.
+    pub fn new(cc: &eframe::CreationContext<'_>, server_override: Option<String>) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
@@ -191,22 +279,145 @@ impl TemplateApp {
 
         cc.egui_ctx.set_fonts(fonts);
 
+        // the web build takes the server from the url, e.g. `index.html?server=http://host:50051`
+        #[cfg(target_arch = "wasm32")]
+        let server_override = server_override.or(cc
+            .integration_info
+            .web_info
+            .location
+            .query_map
+            .get("server")
+            .cloned());
+
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
-            let mut loaded: TemplateApp =
-                eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-            let common_data = common_data::CommonData::default();
-            let common_data_arc = std::sync::Arc::new(std::sync::Mutex::new(common_data));
-            loaded.behavior = TreeBehavior::new(common_data_arc.clone());
-            loaded.common_data = common_data_arc.clone();
-            return loaded;
+        let mut app: TemplateApp = if let Some(storage) = cc.storage {
+            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
+        } else {
+            Default::default()
+        };
+        if let Some(server_address) = server_override {
+            app.connection_settings.apply_override(server_address);
+        }
+        let mut common_data = common_data::CommonData::new(app.connection_settings.endpoints());
+        if let Some(world) = common_data.restore_session(app.session.clone()) {
+            app.world_player.selected_world = Some(world);
+        }
+        let common_data_arc = std::sync::Arc::new(std::sync::Mutex::new(common_data));
+        app.behavior = TreeBehavior::new(common_data_arc.clone());
+        app.common_data = common_data_arc.clone();
+        app
+    }
+}
+
+impl TemplateApp {
+    fn notify(&self, endpoint: &str, status: tonic::Status) {
+        if let Ok(mut cdata) = self.common_data.lock() {
+            cdata.notifications.push(endpoint, &status);
+        }
+    }
+
+    fn export_workspace(&mut self) {
+        let json = {
+            let cdata = match self.common_data.lock() {
+                Ok(cdata) => cdata,
+                Err(_) => return,
+            };
+            let sources = cdata.session(self.world_player.selected_world.as_ref());
+            workspace::Workspace::new(&self.tree, sources).to_json()
+        };
+        let json = match json {
+            Ok(json) => json,
+            Err(e) => {
+                self.notify("export_workspace", tonic::Status::internal(e.to_string()));
+                return;
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.saved_workspace.is_none() {
+            self.saved_workspace = Some(backend_talk::spawn(async move {
+                let file = match rfd::AsyncFileDialog::new()
+                    .add_filter("workspace", &["json"])
+                    .set_file_name("workspace.json")
+                    .save_file()
+                    .await
+                {
+                    Some(file) => file,
+                    None => return Ok(()),
+                };
+                std::fs::write(file.path(), json).map_err(|e| e.to_string())
+            }));
+        }
+        #[cfg(target_arch = "wasm32")]
+        if download("workspace.json", &json).is_none() {
+            self.notify(
+                "export_workspace",
+                tonic::Status::internal("the browser refused the download"),
+            );
+        }
+    }
+
+    /// Replace the layout by the one of a workspace file, and load the files its panes show.
+    fn import_workspace(&mut self, contents: &[u8]) {
+        let mut cdata = match self.common_data.lock() {
+            Ok(cdata) => cdata,
+            Err(_) => return,
+        };
+        match workspace::Workspace::<egui_tiles::Tree<Pane>>::from_json(contents) {
+            Ok(workspace) => {
+                self.tree = workspace.layout;
+                let sources = cdata.retarget_session(workspace.sources);
+                if let Some(world) = cdata.restore_session(sources) {
+                    self.world_player.selected_world = Some(world);
+                }
+            }
+            Err(e) => cdata.notifications.push(
+                "import_workspace",
+                &tonic::Status::invalid_argument(e.to_string()),
+            ),
         }
+    }
 
-        Default::default()
+    fn pick_workspace(&mut self) {
+        if self.picked_workspace.is_none() {
+            self.picked_workspace = Some(backend_talk::spawn(async {
+                let file = rfd::AsyncFileDialog::new()
+                    .add_filter("workspace", &["json"])
+                    .pick_file()
+                    .await?;
+                #[cfg(not(target_arch = "wasm32"))]
+                let contents = std::fs::read(file.path()).map_err(|e| e.to_string());
+                #[cfg(target_arch = "wasm32")]
+                let contents = Ok(file.read().await);
+                Some(contents)
+            }));
+        }
     }
 }
 
+/// Have the browser save `contents` as a file named `file_name`.
+#[cfg(target_arch = "wasm32")]
+fn download(file_name: &str, contents: &str) -> Option<()> {
+    use wasm_bindgen::JsCast;
+    let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(contents));
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(
+        &parts,
+        web_sys::BlobPropertyBag::new().type_("application/json"),
+    )
+    .ok()?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+    let anchor: web_sys::HtmlAnchorElement = web_sys::window()?
+        .document()?
+        .create_element("a")
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).ok()
+}
+
 impl eframe::App for TemplateApp {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
@@ -216,6 +427,7 @@ impl eframe::App for TemplateApp {
                 return;
             }
             let mut cdata = cdata.unwrap();
+            self.session = cdata.session(self.world_player.selected_world.as_ref());
             let current_path = cdata.current_path.clone();
             cdata.current_path = cdata.default_path.clone();
             current_path.clone()
@@ -246,8 +458,33 @@ impl eframe::App for TemplateApp {
             //
             // Data update
             //
-            let selected_world_name = self.world_player.selected_world_name.clone();
-            cdata.update(selected_world_name);
+            let selected_world = self.world_player.selected_world.clone();
+            cdata.update(selected_world);
+            // panes of the restored layout still refer to the ids of the last session
+            let remap = cdata.take_df_id_remap();
+            if !remap.is_empty() {
+                for (_, tile) in self.tree.tiles.iter_mut() {
+                    if let egui_tiles::Tile::Pane(pane) = tile {
+                        pane.remap_df_ids(&remap);
+                    }
+                }
+            }
+
+            // files dropped onto the window, or picked in the browser, open the load dialog
+            let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+            for file in dropped_files.iter() {
+                match modal_window::ModalWindowInput::from_dropped(file) {
+                    Some(input) => cdata.modal_window_input_opt = Some(input),
+                    None => log::warn!("could not read dropped file {}", file.name),
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            if let Some(picked) = self.picked_file.as_ref().and_then(|p| p.ready()) {
+                if let Some(input) = picked {
+                    cdata.modal_window_input_opt = Some(input.clone());
+                }
+                self.picked_file = None;
+            }
 
             //
             // View update
@@ -267,18 +504,57 @@ impl eframe::App for TemplateApp {
                     cdata.modal_window_input_opt = None;
                 }
                 opening_modal_window = true;
-                Some(m_output)
+                Some((m_output, modal_window_input.local_file.clone()))
             } else {
                 None
             };
-            if let Some(m_output) = m_output {
+            if let Some((m_output, local_file)) = m_output {
                 if m_output.action == modal_window::ModalWindowAction::Preview {
-                    cdata.load_data_frame_from_file(&m_output.filepath, &m_output.load_option);
+                    match local_file {
+                        Some(local_file) => cdata.load_local_file(
+                            &m_output.filepath,
+                            local_file,
+                            &m_output.load_option,
+                        ),
+                        None => cdata
+                            .load_data_frame_from_file(&m_output.filepath, &m_output.load_option),
+                    }
                 }
                 if m_output.action == modal_window::ModalWindowAction::Cancel {
                     cdata.remove_preview_data_frame();
                 }
             }
+
+            cdata.notifications.show_toasts(ctx);
+            if let Some(message) = cdata.notifications.take_credentials_rejected() {
+                self.credentials_prompt = Some(message);
+                self.show_connection_settings = true;
+            }
+        }
+        if let Some(picked) = self
+            .picked_workspace
+            .as_ref()
+            .and_then(|p| p.ready())
+            .cloned()
+        {
+            self.picked_workspace = None;
+            match picked {
+                Some(Ok(contents)) => self.import_workspace(&contents),
+                Some(Err(e)) => self.notify("import_workspace", tonic::Status::not_found(e)),
+                None => {}
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(saved) = self
+            .saved_workspace
+            .as_ref()
+            .and_then(|p| p.ready())
+            .cloned()
+        {
+            self.saved_workspace = None;
+            if let Err(e) = saved {
+                self.notify("export_workspace", tonic::Status::internal(e));
+            }
         }
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.set_enabled(!opening_modal_window);
@@ -296,6 +572,56 @@ impl eframe::App for TemplateApp {
 
                 egui::widgets::global_dark_light_mode_buttons(ui);
 
+                // the backend's file system is out of reach of the browser user otherwise
+                #[cfg(target_arch = "wasm32")]
+                if ui.button("open file").clicked() && self.picked_file.is_none() {
+                    self.picked_file = Some(poll_promise::Promise::spawn_local(async {
+                        let file = rfd::AsyncFileDialog::new()
+                            .add_filter("csv", &["csv", "txt"])
+                            .pick_file()
+                            .await?;
+                        Some(modal_window::ModalWindowInput {
+                            filepath: file.file_name(),
+                            local_file: Some(modal_window::LocalFile::Contents(
+                                file.read().await.into(),
+                            )),
+                        })
+                    }));
+                }
+
+                ui.menu_button("workspace", |ui| {
+                    if ui.button("export").clicked() {
+                        ui.close_menu();
+                        self.export_workspace();
+                    }
+                    if ui.button("import").clicked() {
+                        ui.close_menu();
+                        self.pick_workspace();
+                    }
+                });
+
+                if ui.button("connection").clicked() {
+                    self.show_connection_settings = !self.show_connection_settings;
+                }
+                if let Ok(cdata) = self.common_data.lock() {
+                    for (address, state) in cdata.connection_states() {
+                        connection_state_ui(ui, address, state);
+                    }
+                }
+
+                let unread = match self.common_data.lock() {
+                    Ok(cdata) => cdata.notifications.unread(),
+                    Err(_) => 0,
+                };
+                let label = if unread > 0 {
+                    format!("notifications ({})", unread)
+                } else {
+                    "notifications".to_string()
+                };
+                if ui.button(label).clicked() {
+                    self.show_notifications = !self.show_notifications;
+                }
+
                 if ui.button("save").clicked() {
                     if let Some(storage) = _frame.storage_mut() {
                         self.save(storage);
@@ -311,6 +637,42 @@ impl eframe::App for TemplateApp {
                 }
             });
         });
+        let mut show_connection_settings = self.show_connection_settings;
+        egui::Window::new("Connection Settings")
+            .open(&mut show_connection_settings)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "connected to {}",
+                    self.connection_settings.server_addresses().join(", ")
+                ));
+                if let Some(message) = &self.credentials_prompt {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "The server requires credentials ({}). Enter a bearer token or an api key for its profile.",
+                            message
+                        ),
+                    );
+                }
+                if self.connection_settings.show(ui) {
+                    self.credentials_prompt = None;
+                    if let Ok(mut cdata) = self.common_data.lock() {
+                        cdata.connect(self.connection_settings.endpoints());
+                    }
+                }
+            });
+        self.show_connection_settings = show_connection_settings;
+
+        let mut show_notifications = self.show_notifications;
+        egui::Window::new("Notifications")
+            .open(&mut show_notifications)
+            .show(ctx, |ui| {
+                if let Ok(mut cdata) = self.common_data.lock() {
+                    cdata.notifications.show_history(ui);
+                }
+            });
+        self.show_notifications = show_notifications;
+
         egui::SidePanel::left("info").show(ctx, |ui| {
             ui.set_enabled(!opening_modal_window);
 
@@ -324,6 +686,14 @@ impl eframe::App for TemplateApp {
             self.tree.ui(&mut self.behavior, ui);
         });
 
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            egui::Area::new("drop_hint")
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.heading("drop a csv file to load it");
+                });
+        }
+
         egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
             self.world_player.show(ui, self.common_data.clone());
         });
@@ -335,6 +705,7 @@ enum PaneType {
     Plotter2D(plotter_2d::Plotter2D),
     Table(dataframe_table::DataFrameTablePane),
     PerformancePlot(PerformancePlot),
+    NetworkInspector(network_inspector::NetworkInspector),
     None(i32),
 }
 
@@ -408,6 +779,17 @@ impl Pane {
         }
     }
 
+    fn remap_df_ids(
+        &mut self,
+        remap: &std::collections::HashMap<common_data::DataFrameKey, common_data::DataFrameKey>,
+    ) {
+        match &mut self.pane_type {
+            PaneType::Plotter2D(p2d) => p2d.remap_df_ids(remap),
+            PaneType::Table(tb) => tb.remap_df_ids(remap),
+            PaneType::PerformancePlot(_) | PaneType::NetworkInspector(_) | PaneType::None(_) => {}
+        }
+    }
+
     pub fn ui(
         &mut self,
         ui: &mut egui::Ui,
@@ -436,6 +818,9 @@ impl Pane {
                 PaneType::PerformancePlot(pp) => {
                     pp.show(ui, common_data_arc.clone());
                 }
+                PaneType::NetworkInspector(ni) => {
+                    ni.show(ui, common_data_arc.clone());
+                }
                 PaneType::None(_) => {
                     let color = egui::epaint::Hsva::new(0.103 * self.nr as f32, 0.5, 0.5, 1.0);
                     ui.painter().rect_filled(ui.max_rect(), 0.0, color);
@@ -1,199 +1,1516 @@
 use crate::components::modal_window::{self};
+use crate::payload;
+use crate::request::{self, MaybeSend, RequestPolicy, RpcPromise, Subscription, TransferProgress};
+use crate::rpc_log::{ResponseSize, RpcLog};
+use crate::unwrap_or_continue;
 use polars::prelude::*;
 use poll_promise::Promise;
 
 pub use h_analyzer_data::grpc_data_transfer;
 pub use h_analyzer_data::grpc_fs;
 
+pub const DEFAULT_SERVER_ADDRESS: &str = "http://192.168.1.8:50051";
+
+// grpc-web through the browser's fetch api on the web, plain grpc over http2 natively
+#[cfg(target_arch = "wasm32")]
+type RawTransport = tonic_web_wasm_client::Client;
+#[cfg(not(target_arch = "wasm32"))]
+type RawTransport = tonic::transport::Channel;
+// every client is built on this, so no request goes out without the credentials
+type Transport = tonic::service::interceptor::InterceptedService<RawTransport, Credentials>;
+
+// per rpc policies: lookups are cheap and safe to repeat, loading creates state on the server
+const QUERY_POLICY: RequestPolicy = RequestPolicy::idempotent(5, 3);
+const TRANSFER_POLICY: RequestPolicy = RequestPolicy::idempotent(60, 2);
+const WORLD_FRAME_POLICY: RequestPolicy = RequestPolicy::idempotent(10, 2);
+const LOAD_POLICY: RequestPolicy = RequestPolicy::once(120);
+const MODIFY_POLICY: RequestPolicy = RequestPolicy::once(10);
+// not retried, a missed health check is what is being measured
+const HEALTH_POLICY: RequestPolicy = RequestPolicy::once(2);
+
+/// Most single frame requests of a range in flight at once on servers without the range rpc,
+/// browsers only open a few connections per server.
+const MAX_FRAME_REQUESTS_IN_FLIGHT: usize = 4;
+
+/// How often a world subscription on a server without the frame stream asks whether new frames
+/// exist. The interval doubles while nothing new arrives, up to the longer one, which is also the
+/// pause before a broken frame stream is opened again.
+const WORLD_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+const WORLD_IDLE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// Comma separated features a server announces in the response headers of the health check.
+const CAPABILITIES_HEADER: &str = "x-capabilities";
+/// Announced by servers which cut out the columns and rows asked for with `x-projection-bin`.
+const PROJECTION_CAPABILITY: &str = "projection";
+
+/// Run `future` in the background, on the page's event loop on the web and on the runtime shared
+/// by the backends natively.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn spawn<T: Send + 'static>(
+    future: impl std::future::Future<Output = T> + MaybeSend + 'static,
+) -> Promise<T> {
+    Promise::spawn_local(future)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn<T: Send + 'static>(
+    future: impl std::future::Future<Output = T> + MaybeSend + 'static,
+) -> Promise<T> {
+    let (sender, promise) = Promise::new();
+    spawn_detached(async move {
+        sender.send(future.await);
+    });
+    promise
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn_detached(future: impl std::future::Future<Output = ()> + MaybeSend + 'static) {
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_detached(future: impl std::future::Future<Output = ()> + MaybeSend + 'static) {
+    runtime().spawn(future);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start tokio runtime")
+    })
+}
+
+/// Sent as metadata with every request, for servers reachable by others than their owner.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Credentials {
+    None,
+    /// `authorization: Bearer <token>`
+    BearerToken(String),
+    /// `x-api-key: <key>`
+    ApiKey(String),
+}
+
+impl Default for Credentials {
+    fn default() -> Self {
+        Credentials::None
+    }
+}
+
+impl tonic::service::Interceptor for Credentials {
+    fn call(&mut self, mut req: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        let (key, value) = match self {
+            Credentials::None => return Ok(req),
+            Credentials::BearerToken(token) => ("authorization", format!("Bearer {}", token)),
+            Credentials::ApiKey(api_key) => ("x-api-key", api_key.clone()),
+        };
+        let value = value.parse().map_err(|_| {
+            tonic::Status::unauthenticated("credentials contain characters not allowed in metadata")
+        })?;
+        req.metadata_mut().insert(key, value);
+        Ok(req)
+    }
+}
+
+/// Part of a dataframe to fetch, the default is the whole of it.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+pub struct DataFrameProjection {
+    /// only these columns, in this order
+    pub columns: Option<Vec<String>>,
+    /// `(offset, len)` of the rows
+    pub rows: Option<(usize, usize)>,
+}
+
+impl DataFrameProjection {
+    /// No rows, only the names and types of the columns.
+    pub fn schema() -> Self {
+        Self {
+            columns: None,
+            rows: Some((0, 0)),
+        }
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.columns.is_none() && self.rows.is_none()
+    }
+
+    pub fn apply(&self, df: DataFrame) -> PolarsResult<DataFrame> {
+        let df = match &self.columns {
+            Some(columns) => df.select(columns)?,
+            None => df,
+        };
+        Ok(match self.rows {
+            Some((offset, len)) => df.slice(offset as i64, len),
+            None => df,
+        })
+    }
+}
+
+/// A fetched dataframe with the fingerprint of its content, if the backend tells it.
+pub struct FetchedDataFrame {
+    /// none if the content still has the fingerprint asked about
+    pub df: Option<DataFrame>,
+    pub fingerprint: Option<String>,
+}
+
+/// Change of the dataframe list, pushed by the server or found by comparing a refreshed list.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataFrameListChange {
+    Added(grpc_fs::DataFrameInfo),
+    /// the file was reloaded or the id now names another one
+    Updated(grpc_fs::DataFrameInfo),
+    Removed(grpc_fs::DataFrameId),
+}
+
+/// Changes turning `known` into `latest`, `known` is updated to it.
+pub fn df_list_changes(
+    known: &mut std::collections::BTreeMap<u32, grpc_fs::DataFrameInfo>,
+    latest: grpc_fs::DataFrameInfoList,
+) -> Vec<DataFrameListChange> {
+    let mut latest_map = std::collections::BTreeMap::new();
+    for info in latest.list {
+        let id = unwrap_or_continue!(info.id.as_ref()).id;
+        latest_map.insert(id, info);
+    }
+    let mut changes = Vec::new();
+    for id in known.keys() {
+        if !latest_map.contains_key(id) {
+            changes.push(DataFrameListChange::Removed(grpc_fs::DataFrameId {
+                id: *id,
+            }));
+        }
+    }
+    for (id, info) in latest_map.iter() {
+        // servers flag reloaded files instead of telling apart their versions
+        let reloaded = info.load_option.as_ref().map_or(false, |o| o.updated);
+        match known.get(id) {
+            None => changes.push(DataFrameListChange::Added(info.clone())),
+            Some(previous) if previous != info || reloaded => {
+                changes.push(DataFrameListChange::Updated(info.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    *known = latest_map;
+    changes
+}
+
+/// Every rpc the frontend issues. `BackendTalk` talks to a real server,
+/// tests plug in an in-memory implementation instead.
+pub trait Backend {
+    fn server_address(&self) -> &str;
+
+    /// The data lives in this process, so queries on it are evaluated right here.
+    fn is_in_process(&self) -> bool;
+
+    /// Cheap request telling whether the backend is reachable and answering.
+    fn check_health(&self) -> RpcPromise<grpc_fs::Empty>;
+
+    /// Whether the backend cuts out the part of a dataframe a projection asks for, `None` until it
+    /// has answered a health check. Parts of dataframes are only requested from backends which do.
+    fn supports_projection(&self) -> Option<bool>;
+
+    fn load_rosbag2(&self, dirpath: String) -> RpcPromise<grpc_fs::Empty>;
+
+    fn get_world_list(&self) -> RpcPromise<grpc_data_transfer::WorldMetadataList>;
+
+    fn request_default_path(&self) -> RpcPromise<grpc_fs::PathMessage>;
+
+    fn request_list(&self, path: String) -> RpcPromise<grpc_fs::ListResponse>;
+
+    fn save_df_list(&self, df_info_list: Vec<grpc_fs::DataFrameInfo>)
+        -> RpcPromise<grpc_fs::Empty>;
+
+    fn request_get_df_list(&self) -> RpcPromise<grpc_fs::DataFrameInfoList>;
+
+    fn load_df_from_file_request(
+        &self,
+        filepath: String,
+        load_option: grpc_fs::DataFrameLoadOption,
+    ) -> RpcPromise<usize>;
+
+    fn remove_df_request(&self, id: grpc_fs::DataFrameId) -> RpcPromise<grpc_fs::Empty>;
+
+    /// With `if_none_match`, the dataframe is only sent if its fingerprint differs from it.
+    fn get_df_request(
+        &self,
+        id: grpc_fs::DataFrameId,
+        projection: DataFrameProjection,
+        if_none_match: Option<String>,
+        progress: TransferProgress,
+    ) -> RpcPromise<FetchedDataFrame>;
+
+    /// Evaluate `plan`, a serialized polars logical plan reading from dataframe `id`, on the server.
+    /// The plan scans a placeholder with the schema of `id`, see `LazyQueryRequest`.
+    fn run_lazy_query(
+        &self,
+        id: grpc_fs::DataFrameId,
+        plan: String,
+        progress: TransferProgress,
+    ) -> RpcPromise<DataFrame>;
+
+    fn get_world_frame(
+        &self,
+        world_name: String,
+        frame_index: u32,
+    ) -> RpcPromise<h_analyzer_data::WorldFrame>;
+
+    /// `count` consecutive frames starting at `start_index`, in order. Only the ones before the
+    /// first failed frame if some of them could not be fetched.
+    fn get_world_frames(
+        &self,
+        world_name: String,
+        start_index: u32,
+        count: u32,
+    ) -> RpcPromise<Vec<h_analyzer_data::WorldFrame>>;
+
+    /// Frames of `world_name` from `start_index` on, including the ones produced after subscribing,
+    /// pushed by the server as they are produced. Servers without the frame stream are polled.
+    fn subscribe_world_frames(
+        &self,
+        world_name: String,
+        start_index: u32,
+    ) -> Subscription<h_analyzer_data::WorldFrame>;
+
+    /// Changes of the dataframe list as the server pushes them, starting with every dataframe
+    /// listed at the time of subscribing. Servers without the change stream answer with
+    /// `Unimplemented`, their list is only refreshed on demand.
+    fn subscribe_df_list(&self) -> Subscription<DataFrameListChange>;
+}
+
+/// Payload size announced by the server in the response metadata, if any.
+fn expected_size(metadata: &tonic::metadata::MetadataMap) -> Option<usize> {
+    metadata.get("x-total-bytes")?.to_str().ok()?.parse().ok()
+}
+
+/// Request offering the server to compress the chunks it streams back.
+fn compressible_request<T>(message: T) -> tonic::Request<T> {
+    let mut req = tonic::Request::new(message);
+    req.metadata_mut().insert(
+        "x-accept-payload-encoding",
+        tonic::metadata::MetadataValue::from_static(payload::ACCEPTED_ENCODINGS),
+    );
+    req
+}
+
+/// Compression chosen by the server. Servers unaware of it send raw bytes and no header.
+fn payload_encoding(
+    metadata: &tonic::metadata::MetadataMap,
+) -> Result<payload::Encoding, tonic::Status> {
+    let name = metadata
+        .get("x-payload-encoding")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    Ok(payload::Encoding::parse(name)?)
+}
+
+async fn fetch_world_list(
+    transport: Transport,
+) -> Result<grpc_data_transfer::WorldMetadataList, tonic::Status> {
+    let mut query_client =
+        grpc_data_transfer::data_transfer2_d_client::DataTransfer2DClient::new(transport);
+    let req = grpc_data_transfer::Empty {};
+    let resp = query_client.get_world_list(req).await?.into_inner();
+    Ok(resp)
+}
+
+async fn fetch_df_list(transport: Transport) -> Result<grpc_fs::DataFrameInfoList, tonic::Status> {
+    let mut query_client = grpc_fs::polars_service_client::PolarsServiceClient::new(transport);
+    let req = grpc_fs::Empty {};
+    let resp = query_client.get_data_frame_list(req).await?.into_inner();
+    Ok(resp)
+}
+
+async fn fetch_world_frame(
+    transport: Transport,
+    world_name: String,
+    frame_index: u32,
+    progress: TransferProgress,
+) -> Result<h_analyzer_data::WorldFrame, tonic::Status> {
+    let mut query_client =
+        grpc_data_transfer::data_transfer2_d_client::DataTransfer2DClient::new(transport);
+
+    let req = grpc_data_transfer::GetWorldFrameRequest {
+        id: Some(grpc_data_transfer::WorldId { id: world_name }),
+        request_type: grpc_data_transfer::WorldFrameRequestType::FrameIndex.into(),
+        frame_index: frame_index,
+        timestamp: Some(grpc_data_transfer::UnixTimeStamp { value: 0.0 }),
+    };
+    let resp = query_client
+        .get_world_frame(compressible_request(req))
+        .await?;
+    let encoding = payload_encoding(resp.metadata())?;
+    let mut stream = resp.into_inner();
+
+    let mut received = 0;
+    let mut cvec = Vec::new();
+    while let Some(cdata) = stream.message().await? {
+        let chunk_len = cdata.data.len();
+        received += chunk_len;
+        let raw = encoding.decompress(cdata.data)?;
+        progress.add_chunk(chunk_len, raw.len());
+        cvec.extend(raw);
+    }
+    if encoding != payload::Encoding::Identity {
+        log::debug!(
+            "world frame {} compressed {:?}: {} -> {} bytes",
+            frame_index,
+            encoding,
+            received,
+            cvec.len()
+        );
+    }
+    Ok(payload::decode(&cvec)?)
+}
+
+fn finish_logged<T: ResponseSize>(
+    rpc_log: &RpcLog,
+    seq: u64,
+    progress: Option<&TransferProgress>,
+    result: &Result<T, tonic::Status>,
+) {
+    let bytes = match (progress, result) {
+        (Some(progress), _) => Some(progress.stats().bytes),
+        (None, Ok(resp)) => resp.response_size(),
+        (None, Err(_)) => None,
+    };
+    rpc_log.finish(seq, bytes, result.as_ref().map(|_| ()));
+}
+
+/// Feeds a world subscription on servers without the frame stream. New frames are discovered
+/// through the world list and fetched one by one.
+/// Polls which find nothing new are left out of the rpc log, they would crowd out every other call.
+async fn follow_world(
+    transport: Transport,
+    rpc_log: RpcLog,
+    server_address: String,
+    world_name: String,
+    start_index: u32,
+    sender: std::sync::mpsc::Sender<Result<h_analyzer_data::WorldFrame, tonic::Status>>,
+) {
+    let mut next_index = start_index;
+    let mut poll_interval = WORLD_POLL_INTERVAL;
+    loop {
+        let polled = web_time::Instant::now();
+        let world_list =
+            request::run(QUERY_POLICY, None, || fetch_world_list(transport.clone())).await;
+        let total_frame_num = world_list.as_ref().ok().map(|world_list| {
+            world_list
+                .list
+                .iter()
+                .find(|meta| meta.id.as_ref().map(|id| &id.id) == Some(&world_name))
+                .map_or(0, |meta| meta.total_frame_num)
+        });
+        if total_frame_num != Some(next_index) {
+            let seq = rpc_log.start_at(
+                &server_address,
+                "get_world_list",
+                world_name.clone(),
+                polled,
+            );
+            finish_logged(&rpc_log, seq, None, &world_list);
+        }
+        let total_frame_num = match world_list {
+            Ok(_) => total_frame_num.unwrap_or(0),
+            Err(e) => {
+                if sender.send(Err(e)).is_err() {
+                    return;
+                }
+                request::sleep(WORLD_IDLE_POLL_INTERVAL).await;
+                continue;
+            }
+        };
+        if total_frame_num < next_index {
+            // the world has been restarted on the server
+            next_index = 0;
+        }
+        poll_interval = if next_index < total_frame_num {
+            WORLD_POLL_INTERVAL
+        } else {
+            std::cmp::min(poll_interval * 2, WORLD_IDLE_POLL_INTERVAL)
+        };
+        while next_index < total_frame_num {
+            let args = format!("{} frame {}", world_name, next_index);
+            let seq = rpc_log.start(&server_address, "get_world_frame", args);
+            let progress = TransferProgress::default();
+            let frame = request::run(WORLD_FRAME_POLICY, Some(progress.clone()), || {
+                progress.start(None);
+                fetch_world_frame(
+                    transport.clone(),
+                    world_name.clone(),
+                    next_index,
+                    progress.clone(),
+                )
+            })
+            .await;
+            finish_logged(&rpc_log, seq, Some(&progress), &frame);
+            let failed = frame.is_err();
+            if sender.send(frame).is_err() {
+                // nobody is listening anymore
+                return;
+            }
+            if failed {
+                break;
+            }
+            next_index += 1;
+        }
+        request::sleep(poll_interval).await;
+    }
+}
+
+/// Path of the rpc streaming the changes of the dataframe list. Like the lazy query rpc below,
+/// it is not part of the generated protos yet, so its messages are declared here.
+const WATCH_DF_LIST_PATH: &str = "/grpc_fs.PolarsService/WatchDataFrameList";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum DataFrameListChangeKind {
+    Added = 0,
+    Updated = 1,
+    Removed = 2,
+}
+
+/// Entry of the dataframe list change stream, which starts with an `Added` entry for every
+/// dataframe listed at the time of the request.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DataFrameListChangeMessage {
+    #[prost(enumeration = "DataFrameListChangeKind", tag = "1")]
+    pub kind: i32,
+    /// the dataframe as listed now, only its id for removals
+    #[prost(message, optional, tag = "2")]
+    pub info: Option<grpc_fs::DataFrameInfo>,
+}
+
+impl DataFrameListChangeMessage {
+    fn into_change(self) -> Result<DataFrameListChange, tonic::Status> {
+        let kind = self.kind;
+        let info = self
+            .info
+            .ok_or_else(|| tonic::Status::internal("list change without a dataframe"))?;
+        match DataFrameListChangeKind::try_from(kind) {
+            Ok(DataFrameListChangeKind::Added) => Ok(DataFrameListChange::Added(info)),
+            Ok(DataFrameListChangeKind::Updated) => Ok(DataFrameListChange::Updated(info)),
+            Ok(DataFrameListChangeKind::Removed) => {
+                Ok(DataFrameListChange::Removed(info.id.ok_or_else(|| {
+                    tonic::Status::internal("removal without a dataframe id")
+                })?))
+            }
+            Err(_) => Err(tonic::Status::internal(format!(
+                "unknown list change {}",
+                kind
+            ))),
+        }
+    }
+}
+
+/// Client for the rpcs declared here rather than in the generated protos.
+async fn ready_client(
+    transport: Transport,
+) -> Result<tonic::client::Grpc<Transport>, tonic::Status> {
+    let mut grpc = tonic::client::Grpc::new(transport);
+    grpc.ready().await.map_err(|e| {
+        let e: tonic::codegen::StdError = e.into();
+        tonic::Status::unknown(format!("service was not ready: {}", e))
+    })?;
+    Ok(grpc)
+}
+
+/// Pass the changes streamed by the server on to `sender`, until either of them hangs up.
+async fn forward_df_list_changes(
+    transport: Transport,
+    sender: &std::sync::mpsc::Sender<Result<DataFrameListChange, tonic::Status>>,
+) -> Result<grpc_fs::Empty, tonic::Status> {
+    let mut grpc = ready_client(transport).await?;
+    let resp = grpc
+        .server_streaming(
+            tonic::Request::new(grpc_fs::Empty {}),
+            tonic::codegen::http::uri::PathAndQuery::from_static(WATCH_DF_LIST_PATH),
+            tonic::codec::ProstCodec::<grpc_fs::Empty, DataFrameListChangeMessage>::default(),
+        )
+        .await?;
+    let mut stream = resp.into_inner();
+    while let Some(message) = stream.message().await? {
+        if sender.send(Ok(message.into_change()?)).is_err() {
+            // nobody is listening anymore
+            return Ok(grpc_fs::Empty {});
+        }
+    }
+    Err(tonic::Status::unavailable(
+        "the server ended the dataframe list change stream",
+    ))
+}
+
+/// Feeds a dataframe list subscription. Its end is logged, and passed on unless unsubscribed.
+async fn watch_df_list(
+    transport: Transport,
+    rpc_log: RpcLog,
+    server_address: String,
+    sender: std::sync::mpsc::Sender<Result<DataFrameListChange, tonic::Status>>,
+) {
+    let seq = rpc_log.start(&server_address, "watch_df_list", String::new());
+    let result = forward_df_list_changes(transport, &sender).await;
+    finish_logged(&rpc_log, seq, None, &result);
+    if let Err(e) = result {
+        let _ = sender.send(Err(e));
+    }
+}
+
+/// Path of the rpc streaming the frames of a world as they are produced, declared here like the
+/// dataframe list change stream.
+const WATCH_WORLD_FRAMES_PATH: &str = "/grpc_data_transfer.DataTransfer2D/WatchWorldFrames";
+
+/// Request of the world frame stream, which starts with the frames already produced from
+/// `start_index` on.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WorldFramesRequest {
+    #[prost(message, optional, tag = "1")]
+    pub id: Option<grpc_data_transfer::WorldId>,
+    #[prost(uint32, tag = "2")]
+    pub start_index: u32,
+}
+
+/// Chunk of a streamed world frame, framed and compressed like the chunks of `get_world_frame`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WorldFrameChunk {
+    #[prost(uint32, tag = "1")]
+    pub frame_index: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+    /// set on the last chunk of every frame
+    #[prost(bool, tag = "3")]
+    pub last: bool,
+}
+
+/// Path of the rpc streaming a range of frames in one call, answered with the chunks of the world
+/// frame stream.
+const GET_WORLD_FRAME_RANGE_PATH: &str = "/grpc_data_transfer.DataTransfer2D/GetWorldFrameRange";
+
+/// Request of `count` consecutive frames from `start_index` on. The server ends the stream after
+/// the last frame of the range, or earlier if the world has no more frames.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WorldFrameRangeRequest {
+    #[prost(message, optional, tag = "1")]
+    pub id: Option<grpc_data_transfer::WorldId>,
+    #[prost(uint32, tag = "2")]
+    pub start_index: u32,
+    #[prost(uint32, tag = "3")]
+    pub count: u32,
+}
+
+/// Puts the frames of a world frame stream back together from their chunks.
+struct WorldFrameAssembler {
+    encoding: payload::Encoding,
+    bytes: Vec<u8>,
+}
+
+impl WorldFrameAssembler {
+    /// The frame `chunk` completes, with its index.
+    fn push(
+        &mut self,
+        chunk: WorldFrameChunk,
+        progress: &TransferProgress,
+    ) -> Result<Option<(u32, h_analyzer_data::WorldFrame)>, tonic::Status> {
+        let received = chunk.data.len();
+        let raw = self.encoding.decompress(chunk.data)?;
+        progress.add_chunk(received, raw.len());
+        self.bytes.extend(raw);
+        if !chunk.last {
+            return Ok(None);
+        }
+        let frame = payload::decode(&std::mem::take(&mut self.bytes))?;
+        Ok(Some((chunk.frame_index, frame)))
+    }
+}
+
+/// Open the frame stream at `path` and hand every frame to `on_frame`, until it returns false.
+async fn stream_world_frames<R: prost::Message + 'static>(
+    transport: Transport,
+    path: &'static str,
+    request: R,
+    progress: &TransferProgress,
+    mut on_frame: impl FnMut(u32, h_analyzer_data::WorldFrame) -> bool,
+) -> Result<grpc_fs::Empty, tonic::Status> {
+    let mut grpc = ready_client(transport).await?;
+    let resp = grpc
+        .server_streaming(
+            compressible_request(request),
+            tonic::codegen::http::uri::PathAndQuery::from_static(path),
+            tonic::codec::ProstCodec::<R, WorldFrameChunk>::default(),
+        )
+        .await?;
+    let mut assembler = WorldFrameAssembler {
+        encoding: payload_encoding(resp.metadata())?,
+        bytes: Vec::new(),
+    };
+    let mut stream = resp.into_inner();
+    while let Some(chunk) = stream.message().await? {
+        if let Some((frame_index, frame)) = assembler.push(chunk, progress)? {
+            if !on_frame(frame_index, frame) {
+                break;
+            }
+        }
+    }
+    Ok(grpc_fs::Empty {})
+}
+
+/// Frames `start_index..end_index` as concurrent single frame requests, for servers without the
+/// range rpc. Only the ones before the first failed frame if some could not be fetched.
+async fn fetch_world_frames_singly(
+    transport: Transport,
+    world_name: String,
+    start_index: u32,
+    end_index: u32,
+    progress: TransferProgress,
+) -> Result<Vec<h_analyzer_data::WorldFrame>, tonic::Status> {
+    use futures_util::StreamExt;
+    let mut frames = futures_util::stream::iter(start_index..end_index)
+        .map(|idx| fetch_world_frame(transport.clone(), world_name.clone(), idx, progress.clone()))
+        .buffered(MAX_FRAME_REQUESTS_IN_FLIGHT);
+    let mut received = Vec::new();
+    while let Some(frame) = frames.next().await {
+        match frame {
+            Ok(frame) => received.push(frame),
+            Err(e) if received.is_empty() => return Err(e),
+            // the frames before are kept, the next range starts at the failed one
+            Err(e) => {
+                log::warn!("range of frames cut short: {}", e);
+                break;
+            }
+        }
+    }
+    Ok(received)
+}
+
+/// `count` frames from `start_index` on in one streamed call. Servers answering `Unimplemented`
+/// are remembered in `ranges_unimplemented` and asked frame by frame from then on.
+async fn fetch_world_frame_range(
+    transport: Transport,
+    world_name: String,
+    start_index: u32,
+    count: u32,
+    progress: TransferProgress,
+    ranges_unimplemented: std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> Result<Vec<h_analyzer_data::WorldFrame>, tonic::Status> {
+    use std::sync::atomic::Ordering;
+    let end_index = start_index.saturating_add(count);
+    if ranges_unimplemented.load(Ordering::Relaxed) {
+        return fetch_world_frames_singly(transport, world_name, start_index, end_index, progress)
+            .await;
+    }
+    let request = WorldFrameRangeRequest {
+        id: Some(grpc_data_transfer::WorldId {
+            id: world_name.clone(),
+        }),
+        start_index: start_index,
+        count: count,
+    };
+    let mut received = Vec::new();
+    let streamed = stream_world_frames(
+        transport.clone(),
+        GET_WORLD_FRAME_RANGE_PATH,
+        request,
+        &progress,
+        |_, frame| {
+            received.push(frame);
+            true
+        },
+    )
+    .await;
+    match streamed {
+        Ok(_) => Ok(received),
+        Err(e) if e.code() == tonic::Code::Unimplemented => {
+            log::info!("no range rpc for world frames, fetching them one by one");
+            ranges_unimplemented.store(true, Ordering::Relaxed);
+            fetch_world_frames_singly(transport, world_name, start_index, end_index, progress).await
+        }
+        Err(e) if received.is_empty() => Err(e),
+        // the frames before are kept, the next range starts at the failed one
+        Err(e) => {
+            log::warn!("range of frames cut short: {}", e);
+            Ok(received)
+        }
+    }
+}
+
+/// Feeds a world subscription from the server's frame stream, opened again from the next frame
+/// whenever it breaks off. Servers answering `Unimplemented` are polled by `follow_world` instead.
+async fn watch_world(
+    transport: Transport,
+    rpc_log: RpcLog,
+    server_address: String,
+    world_name: String,
+    start_index: u32,
+    sender: std::sync::mpsc::Sender<Result<h_analyzer_data::WorldFrame, tonic::Status>>,
+) {
+    let mut next_index = start_index;
+    loop {
+        let args = format!("{} from frame {}", world_name, next_index);
+        let seq = rpc_log.start(&server_address, "watch_world_frames", args);
+        let progress = TransferProgress::default();
+        progress.start(None);
+        let request = WorldFramesRequest {
+            id: Some(grpc_data_transfer::WorldId {
+                id: world_name.clone(),
+            }),
+            start_index: next_index,
+        };
+        let mut unsubscribed = false;
+        let result = stream_world_frames(
+            transport.clone(),
+            WATCH_WORLD_FRAMES_PATH,
+            request,
+            &progress,
+            |frame_index, frame| {
+                next_index = frame_index.saturating_add(1);
+                unsubscribed = sender.send(Ok(frame)).is_err();
+                !unsubscribed
+            },
+        )
+        .await;
+        finish_logged(&rpc_log, seq, Some(&progress), &result);
+        let e = match result {
+            // nobody is listening anymore
+            _ if unsubscribed => return,
+            Ok(_) => tonic::Status::unavailable("the server ended the world frame stream"),
+            Err(e) if e.code() == tonic::Code::Unimplemented => {
+                log::info!(
+                    "{} does not stream world frames, polling for them",
+                    server_address
+                );
+                follow_world(
+                    transport,
+                    rpc_log,
+                    server_address,
+                    world_name,
+                    next_index,
+                    sender,
+                )
+                .await;
+                return;
+            }
+            Err(e) => e,
+        };
+        if sender.send(Err(e)).is_err() {
+            return;
+        }
+        request::sleep(WORLD_IDLE_POLL_INTERVAL).await;
+    }
+}
+
+/// Path of the rpc evaluating lazy queries. Servers implementing it have to match the messages
+/// declared here.
+const RUN_LAZY_QUERY_PATH: &str = "/grpc_fs.PolarsService/RunLazyQuery";
+
+/// Request of the lazy query rpc. `plan` is a polars logical plan as json whose only source is
+/// a `DataFrameScan` of an empty dataframe with the schema of dataframe `id`. The server has to
+/// replace that placeholder by the data of `id` before evaluating the plan, as the frontend
+/// never has more than the schema.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LazyQueryRequest {
+    #[prost(message, optional, tag = "1")]
+    pub id: Option<grpc_fs::DataFrameId>,
+    #[prost(string, tag = "2")]
+    pub plan: String,
+}
+
+/// Chunk of the result of a lazy query, framed and compressed like the chunks of a dataframe.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LazyQueryChunk {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+}
+
+/// Evaluate `plan` against dataframe `id` on the server and stream the result. The plan goes in
+/// the request message, it easily outgrows what servers and proxies accept as a header.
+async fn evaluate_lazy_query(
+    transport: Transport,
+    id: grpc_fs::DataFrameId,
+    plan: String,
+    progress: TransferProgress,
+) -> Result<DataFrame, tonic::Status> {
+    let mut grpc = ready_client(transport).await?;
+    let mut req = compressible_request(LazyQueryRequest {
+        id: Some(id),
+        plan: plan,
+    });
+    req.metadata_mut().insert(
+        "x-accept-format",
+        tonic::metadata::MetadataValue::from_static(payload::ACCEPTED_DF_FORMATS),
+    );
+    let resp = grpc
+        .server_streaming(
+            req,
+            tonic::codegen::http::uri::PathAndQuery::from_static(RUN_LAZY_QUERY_PATH),
+            tonic::codec::ProstCodec::<LazyQueryRequest, LazyQueryChunk>::default(),
+        )
+        .await?;
+    receive_data_frame(resp, |chunk| chunk.data, &progress).await
+}
+
+/// Read the chunks of a serialized dataframe, in whichever encoding and format the server chose.
+async fn receive_data_frame<C>(
+    resp: tonic::Response<tonic::Streaming<C>>,
+    data: impl Fn(C) -> Vec<u8>,
+    progress: &TransferProgress,
+) -> Result<DataFrame, tonic::Status> {
+    progress.start(expected_size(resp.metadata()));
+    let encoding = payload_encoding(resp.metadata())?;
+    let mut stream = resp.into_inner();
+
+    let mut decoder = payload::DataFrameDecoder::default();
+    while let Some(chunk) = stream.message().await? {
+        let chunk = data(chunk);
+        let received = chunk.len();
+        let raw = encoding.decompress(chunk)?;
+        progress.add_chunk(received, raw.len());
+        decoder.push(raw)?;
+    }
+    let stats = progress.stats();
+    log::info!(
+        "dataframe received as {:?}: {} bytes, ratio {:.2}",
+        encoding,
+        stats.bytes,
+        stats.compression_ratio()
+    );
+    Ok(decoder.finish()?)
+}
+
+/// Stream dataframe `id` or the part given by `projection`.
+async fn fetch_data_frame(
+    transport: Transport,
+    id: grpc_fs::DataFrameId,
+    projection: DataFrameProjection,
+    if_none_match: Option<String>,
+    progress: TransferProgress,
+) -> Result<FetchedDataFrame, tonic::Status> {
+    let mut query_client = grpc_fs::polars_service_client::PolarsServiceClient::new(transport);
+
+    // the server picks the wire format, arrow if it supports it
+    let mut req = compressible_request(id.clone());
+    req.metadata_mut().insert(
+        "x-accept-format",
+        tonic::metadata::MetadataValue::from_static(payload::ACCEPTED_DF_FORMATS),
+    );
+    // column names are not necessarily ascii, so the projection goes as binary metadata
+    if !projection.is_whole() {
+        // without it the server would send the whole dataframe
+        let projection = bincode::serialize(&projection).map_err(|e| {
+            tonic::Status::internal(format!("failed to serialize projection: {}", e))
+        })?;
+        req.metadata_mut().insert_bin(
+            "x-projection-bin",
+            tonic::metadata::MetadataValue::from_bytes(&projection),
+        );
+    }
+    // fingerprints are whatever the server makes of the file, e.g. its modification time
+    if let Some(fingerprint) = if_none_match {
+        match fingerprint.parse() {
+            Ok(value) => {
+                req.metadata_mut().insert("x-if-none-match", value);
+            }
+            // the dataframe is then sent even if unchanged
+            Err(_) => log::warn!(
+                "fingerprint {:?} of dataframe {} is no valid header value, fetching it unconditionally",
+                fingerprint,
+                id.id
+            ),
+        }
+    }
+    let resp = query_client.get_data_frame(req).await?;
+    let fingerprint = resp
+        .metadata()
+        .get("x-content-fingerprint")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    if resp.metadata().contains_key("x-not-modified") {
+        return Ok(FetchedDataFrame {
+            df: None,
+            fingerprint: fingerprint,
+        });
+    }
+    let projected = resp.metadata().contains_key("x-projection-applied");
+    let df = receive_data_frame(resp, |cdata| cdata.data, &progress).await?;
+    let df = if projected {
+        df
+    } else {
+        // parts are only asked of servers announcing projections, this is for the odd one skipping it
+        projection
+            .apply(df)
+            .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?
+    };
+    Ok(FetchedDataFrame {
+        df: Some(df),
+        fingerprint: fingerprint,
+    })
+}
+
+/// Rejections name the server, so that the user knows which profile needs credentials.
+fn name_rejecting_server(server_address: &str, status: tonic::Status) -> tonic::Status {
+    match status.code() {
+        tonic::Code::Unauthenticated | tonic::Code::PermissionDenied => tonic::Status::new(
+            status.code(),
+            format!("{}: {}", server_address, status.message()),
+        ),
+        _ => status,
+    }
+}
+
 pub struct BackendTalk {
     server_address: String,
+    credentials: Credentials,
+    rpc_log: RpcLog,
+    // learnt from the capabilities sent along with health checks
+    supports_projection: std::sync::Arc<std::sync::Mutex<Option<bool>>>,
+    // set once the server answered the world frame range rpc with `Unimplemented`
+    frame_ranges_unimplemented: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    #[cfg(not(target_arch = "wasm32"))]
+    channel: Option<tonic::transport::Channel>,
 }
 
-use tonic_web_wasm_client::Client;
 impl BackendTalk {
-    pub fn default() -> Self {
+    #[cfg(target_arch = "wasm32")]
+    pub fn new(server_address: String, credentials: Credentials, rpc_log: RpcLog) -> Self {
         BackendTalk {
-            server_address: "http://192.168.1.8:50051".to_string(),
+            server_address: server_address,
+            credentials: credentials,
+            rpc_log: rpc_log,
+            supports_projection: Default::default(),
+            frame_ranges_unimplemented: Default::default(),
         }
     }
 
-    pub fn load_rosbag2(&self, dirpath: String) -> Promise<Result<grpc_fs::Empty, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_fs::polars_service_client::PolarsServiceClient::new(Client::new(addr));
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(server_address: String, credentials: Credentials, rpc_log: RpcLog) -> Self {
+        // the channel connects on first use and reconnects by itself, so it is shared by all requests
+        let _guard = runtime().enter();
+        let channel = tonic::transport::Endpoint::from_shared(server_address.clone())
+            .map(|endpoint| endpoint.connect_lazy())
+            .map_err(|e| log::error!("invalid server address {}: {}", server_address, e))
+            .ok();
+        BackendTalk {
+            server_address: server_address,
+            credentials: credentials,
+            rpc_log: rpc_log,
+            supports_projection: Default::default(),
+            frame_ranges_unimplemented: Default::default(),
+            channel: channel,
+        }
+    }
 
-            let req = grpc_fs::PathMessage {
-                path: dirpath.clone(),
-            };
+    #[cfg(target_arch = "wasm32")]
+    fn transport(&self) -> Result<Transport, tonic::Status> {
+        Ok(Transport::new(
+            tonic_web_wasm_client::Client::new(self.server_address.clone()),
+            self.credentials.clone(),
+        ))
+    }
 
-            let resp = query_client.load_rosbag2(req).await?.into_inner();
-            Ok(resp)
-        })
+    #[cfg(not(target_arch = "wasm32"))]
+    fn transport(&self) -> Result<Transport, tonic::Status> {
+        let channel = self.channel.clone().ok_or_else(|| {
+            tonic::Status::invalid_argument(format!(
+                "invalid server address: {}",
+                self.server_address
+            ))
+        })?;
+        Ok(Transport::new(channel, self.credentials.clone()))
     }
 
-    pub fn get_world_list(
+    /// Run `attempt` on the runtime under `policy`. Every retry gets a fresh client on the shared transport.
+    /// The call is logged as `method` with `args`, streamed responses count their bytes on `progress`.
+    fn call<T, F, Fut>(
         &self,
-    ) -> Promise<Result<grpc_data_transfer::WorldMetadataList, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_data_transfer::data_transfer2_d_client::DataTransfer2DClient::new(
-                    Client::new(addr),
-                );
-            let req = grpc_data_transfer::Empty {};
-            let resp = query_client.get_world_list(req).await?.into_inner();
-            Ok(resp)
-        })
+        method: &'static str,
+        args: String,
+        policy: RequestPolicy,
+        progress: Option<TransferProgress>,
+        attempt: F,
+    ) -> RpcPromise<T>
+    where
+        T: ResponseSize + Send + 'static,
+        F: Fn(Transport) -> Fut + MaybeSend + 'static,
+        Fut: std::future::Future<Output = Result<T, tonic::Status>> + MaybeSend + 'static,
+    {
+        let seq = self.rpc_log.start(&self.server_address, method, args);
+        let transport = match self.transport() {
+            Ok(transport) => transport,
+            Err(e) => {
+                self.rpc_log.finish(seq, None, Err(&e));
+                return RpcPromise::from_ready(Err(e));
+            }
+        };
+        let (future, abort_handle) =
+            request::cancellable(request::run(policy, progress.clone(), move || {
+                attempt(transport.clone())
+            }));
+        let server_address = self.server_address.clone();
+        let rpc_log = self.rpc_log.clone();
+        let promise = spawn(async move {
+            let result = future
+                .await
+                .map_err(|e| name_rejecting_server(&server_address, e));
+            finish_logged(&rpc_log, seq, progress.as_ref(), &result);
+            result
+        });
+        RpcPromise::new(promise, abort_handle)
     }
+}
 
-    pub fn request_default_path(&self) -> Promise<Result<grpc_fs::PathMessage, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_fs::file_system_client::FileSystemClient::new(Client::new(addr));
-            let req = grpc_fs::Empty {};
+impl Backend for BackendTalk {
+    fn is_in_process(&self) -> bool {
+        false
+    }
 
-            let resp = query_client.default_path(req).await?.into_inner();
-            log::info!("resp: {:?}", resp);
-            Ok(resp)
-        })
+    fn server_address(&self) -> &str {
+        self.server_address.as_str()
     }
 
-    pub fn request_list(
-        &self,
-        path: String,
-    ) -> Promise<Result<grpc_fs::ListResponse, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_fs::file_system_client::FileSystemClient::new(Client::new(addr));
-            let req = grpc_fs::PathMessage {
-                path: path.to_string(),
-            };
-
-            let resp = query_client.list(req).await?.into_inner();
-            Ok(resp)
-        })
+    fn check_health(&self) -> RpcPromise<grpc_fs::Empty> {
+        // there is no dedicated rpc, the default path is the cheapest one every server answers
+        let supports_projection = self.supports_projection.clone();
+        self.call(
+            "check_health",
+            String::new(),
+            HEALTH_POLICY,
+            None,
+            move |transport| {
+                let supports_projection = supports_projection.clone();
+                async move {
+                    let mut query_client =
+                        grpc_fs::file_system_client::FileSystemClient::new(transport);
+                    let resp = query_client.default_path(grpc_fs::Empty {}).await?;
+                    // servers predating the header cannot cut out anything
+                    let capabilities = resp
+                        .metadata()
+                        .get(CAPABILITIES_HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default();
+                    *supports_projection.lock().unwrap() = Some(
+                        capabilities
+                            .split(',')
+                            .any(|capability| capability.trim() == PROJECTION_CAPABILITY),
+                    );
+                    Ok(grpc_fs::Empty {})
+                }
+            },
+        )
     }
 
-    pub fn save_df_list(
+    fn supports_projection(&self) -> Option<bool> {
+        *self.supports_projection.lock().unwrap()
+    }
+
+    fn load_rosbag2(&self, dirpath: String) -> RpcPromise<grpc_fs::Empty> {
+        self.call(
+            "load_rosbag2",
+            dirpath.clone(),
+            LOAD_POLICY,
+            None,
+            move |transport| {
+                let dirpath = dirpath.clone();
+                async move {
+                    let mut query_client =
+                        grpc_fs::polars_service_client::PolarsServiceClient::new(transport);
+
+                    let req = grpc_fs::PathMessage { path: dirpath };
+
+                    let resp = query_client.load_rosbag2(req).await?.into_inner();
+                    Ok(resp)
+                }
+            },
+        )
+    }
+
+    fn get_world_list(&self) -> RpcPromise<grpc_data_transfer::WorldMetadataList> {
+        self.call(
+            "get_world_list",
+            String::new(),
+            QUERY_POLICY,
+            None,
+            fetch_world_list,
+        )
+    }
+
+    fn request_default_path(&self) -> RpcPromise<grpc_fs::PathMessage> {
+        self.call(
+            "request_default_path",
+            String::new(),
+            QUERY_POLICY,
+            None,
+            move |transport| async move {
+                let mut query_client =
+                    grpc_fs::file_system_client::FileSystemClient::new(transport);
+                let req = grpc_fs::Empty {};
+
+                let resp = query_client.default_path(req).await?.into_inner();
+                log::info!("resp: {:?}", resp);
+                Ok(resp)
+            },
+        )
+    }
+
+    fn request_list(&self, path: String) -> RpcPromise<grpc_fs::ListResponse> {
+        self.call(
+            "request_list",
+            path.clone(),
+            QUERY_POLICY,
+            None,
+            move |transport| {
+                let path = path.clone();
+                async move {
+                    let mut query_client =
+                        grpc_fs::file_system_client::FileSystemClient::new(transport);
+                    let req = grpc_fs::PathMessage { path: path };
+
+                    let resp = query_client.list(req).await?.into_inner();
+                    Ok(resp)
+                }
+            },
+        )
+    }
+
+    fn save_df_list(
         &self,
         df_info_list: Vec<grpc_fs::DataFrameInfo>,
-    ) -> Promise<Result<grpc_fs::Empty, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_fs::polars_service_client::PolarsServiceClient::new(Client::new(addr));
+    ) -> RpcPromise<grpc_fs::Empty> {
+        let args = format!("{} dataframes", df_info_list.len());
+        self.call("save_df_list", args, QUERY_POLICY, None, move |transport| {
+            let df_info_list = df_info_list.clone();
+            async move {
+                let mut query_client =
+                    grpc_fs::polars_service_client::PolarsServiceClient::new(transport);
 
-            let req = grpc_fs::DataFrameInfoList { list: df_info_list };
+                let req = grpc_fs::DataFrameInfoList { list: df_info_list };
 
-            let resp = query_client.save_data_frame_list(req).await?.into_inner();
-            Ok(resp)
+                let resp = query_client.save_data_frame_list(req).await?.into_inner();
+                Ok(resp)
+            }
         })
     }
 
-    pub fn request_get_df_list(
-        &self,
-    ) -> Promise<Result<grpc_fs::DataFrameInfoList, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_fs::polars_service_client::PolarsServiceClient::new(Client::new(addr));
-            let req = grpc_fs::Empty {};
-
-            let resp = query_client.get_data_frame_list(req).await?.into_inner();
-            Ok(resp)
-        })
+    fn request_get_df_list(&self) -> RpcPromise<grpc_fs::DataFrameInfoList> {
+        self.call(
+            "request_get_df_list",
+            String::new(),
+            QUERY_POLICY,
+            None,
+            fetch_df_list,
+        )
     }
 
-    pub fn load_df_from_file_request(
+    fn load_df_from_file_request(
         &self,
         filepath: String,
         load_option: h_analyzer_data::grpc_fs::DataFrameLoadOption,
-    ) -> Promise<Result<usize, tonic::Status>> {
-        let addr = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_fs::polars_service_client::PolarsServiceClient::new(Client::new(addr));
-            let req = grpc_fs::FileLoadRequest {
-                filename: filepath,
-                load_option: Some(load_option),
-            };
-
-            let resp = query_client
-                .load_data_frame_from_file(req)
-                .await?
-                .into_inner();
-            Ok(resp.id as usize)
-        })
+    ) -> RpcPromise<usize> {
+        let args = filepath.clone();
+        self.call(
+            "load_df_from_file_request",
+            args,
+            LOAD_POLICY,
+            None,
+            move |transport| {
+                let req = grpc_fs::FileLoadRequest {
+                    filename: filepath.clone(),
+                    load_option: Some(load_option.clone()),
+                };
+                async move {
+                    let mut query_client =
+                        grpc_fs::polars_service_client::PolarsServiceClient::new(transport);
+
+                    let resp = query_client
+                        .load_data_frame_from_file(req)
+                        .await?
+                        .into_inner();
+                    Ok(resp.id as usize)
+                }
+            },
+        )
     }
 
-    pub fn remove_df_request(
+    fn remove_df_request(
         &self,
         id: h_analyzer_data::grpc_fs::DataFrameId,
-    ) -> Promise<Result<h_analyzer_data::grpc_fs::Empty, tonic::Status>> {
-        let base_url = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client = grpc_fs::polars_service_client::PolarsServiceClient::new(
-                Client::new(base_url.to_string()),
-            );
+    ) -> RpcPromise<h_analyzer_data::grpc_fs::Empty> {
+        let args = format!("id {}", id.id);
+        self.call(
+            "remove_df_request",
+            args,
+            MODIFY_POLICY,
+            None,
+            move |transport| {
+                let id = id.clone();
+                async move {
+                    let mut query_client =
+                        grpc_fs::polars_service_client::PolarsServiceClient::new(transport);
 
-            Ok(query_client.remove_data_frame(id).await?.into_inner())
-        })
+                    Ok(query_client.remove_data_frame(id).await?.into_inner())
+                }
+            },
+        )
     }
 
-    pub fn get_df_request(
+    fn get_df_request(
         &self,
         id: h_analyzer_data::grpc_fs::DataFrameId,
-    ) -> Promise<Result<DataFrame, tonic::Status>> {
-        let base_url = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client = grpc_fs::polars_service_client::PolarsServiceClient::new(
-                Client::new(base_url.to_string()),
-            );
-
-            let mut stream = query_client.get_data_frame(id).await?.into_inner();
+        projection: DataFrameProjection,
+        if_none_match: Option<String>,
+        progress: TransferProgress,
+    ) -> RpcPromise<FetchedDataFrame> {
+        let args = match &if_none_match {
+            Some(fingerprint) => format!("id {} {:?}, if not {}", id.id, projection, fingerprint),
+            None => format!("id {} {:?}", id.id, projection),
+        };
+        self.call(
+            "get_df_request",
+            args,
+            TRANSFER_POLICY,
+            Some(progress.clone()),
+            move |transport| {
+                fetch_data_frame(
+                    transport,
+                    id.clone(),
+                    projection.clone(),
+                    if_none_match.clone(),
+                    progress.clone(),
+                )
+            },
+        )
+    }
 
-            let mut cvec = Vec::new();
-            while let Some(cdata) = stream.message().await? {
-                for v in cdata.data {
-                    cvec.push(v);
-                }
-            }
-            let df = bincode::deserialize_from(cvec.clone().as_slice()).unwrap_or_default();
-            Ok(df)
-        })
+    fn run_lazy_query(
+        &self,
+        id: h_analyzer_data::grpc_fs::DataFrameId,
+        plan: String,
+        progress: TransferProgress,
+    ) -> RpcPromise<DataFrame> {
+        let args = format!("id {}, plan of {} bytes", id.id, plan.len());
+        self.call(
+            "run_lazy_query",
+            args,
+            TRANSFER_POLICY,
+            Some(progress.clone()),
+            move |transport| {
+                evaluate_lazy_query(transport, id.clone(), plan.clone(), progress.clone())
+            },
+        )
     }
 
-    pub fn get_world_frame(
+    fn get_world_frame(
         &self,
         world_name: String,
         frame_index: u32,
-    ) -> Promise<Result<h_analyzer_data::WorldFrame, tonic::Status>> {
-        let base_url = self.server_address.clone();
-        Promise::spawn_local(async move {
-            let mut query_client =
-                grpc_data_transfer::data_transfer2_d_client::DataTransfer2DClient::new(
-                    Client::new(base_url),
+    ) -> RpcPromise<h_analyzer_data::WorldFrame> {
+        let args = format!("{} frame {}", world_name, frame_index);
+        let progress = TransferProgress::default();
+        self.call(
+            "get_world_frame",
+            args,
+            WORLD_FRAME_POLICY,
+            Some(progress.clone()),
+            move |transport| {
+                progress.start(None);
+                fetch_world_frame(transport, world_name.clone(), frame_index, progress.clone())
+            },
+        )
+    }
+
+    fn get_world_frames(
+        &self,
+        world_name: String,
+        start_index: u32,
+        count: u32,
+    ) -> RpcPromise<Vec<h_analyzer_data::WorldFrame>> {
+        let args = format!(
+            "{} frames {}..{}",
+            world_name,
+            start_index,
+            start_index.saturating_add(count)
+        );
+        let progress = TransferProgress::default();
+        let ranges_unimplemented = self.frame_ranges_unimplemented.clone();
+        self.call(
+            "get_world_frames",
+            args,
+            WORLD_FRAME_POLICY,
+            Some(progress.clone()),
+            move |transport| {
+                progress.start(None);
+                fetch_world_frame_range(
+                    transport,
+                    world_name.clone(),
+                    start_index,
+                    count,
+                    progress.clone(),
+                    ranges_unimplemented.clone(),
+                )
+            },
+        )
+    }
+
+    fn subscribe_world_frames(
+        &self,
+        world_name: String,
+        start_index: u32,
+    ) -> Subscription<h_analyzer_data::WorldFrame> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (abort_handle, abort_registration) = futures_util::future::AbortHandle::new_pair();
+        match self.transport() {
+            Ok(transport) => {
+                let future = futures_util::future::Abortable::new(
+                    watch_world(
+                        transport,
+                        self.rpc_log.clone(),
+                        self.server_address.clone(),
+                        world_name,
+                        start_index,
+                        sender,
+                    ),
+                    abort_registration,
                 );
+                spawn_detached(async move {
+                    let _ = future.await;
+                });
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
+        Subscription::new(receiver, abort_handle)
+    }
 
-            let req = grpc_data_transfer::GetWorldFrameRequest {
-                id: Some(grpc_data_transfer::WorldId { id: world_name }),
-                request_type: grpc_data_transfer::WorldFrameRequestType::FrameIndex.into(),
-                frame_index: frame_index,
-                timestamp: Some(grpc_data_transfer::UnixTimeStamp { value: 0.0 }),
-            };
-            let mut stream = query_client.get_world_frame(req).await?.into_inner();
-
-            let mut cvec = Vec::new();
-            while let Some(cdata) = stream.message().await? {
-                for v in cdata.data {
-                    cvec.push(v);
-                }
+    fn subscribe_df_list(&self) -> Subscription<DataFrameListChange> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (abort_handle, abort_registration) = futures_util::future::AbortHandle::new_pair();
+        match self.transport() {
+            Ok(transport) => {
+                let future = futures_util::future::Abortable::new(
+                    watch_df_list(
+                        transport,
+                        self.rpc_log.clone(),
+                        self.server_address.clone(),
+                        sender,
+                    ),
+                    abort_registration,
+                );
+                spawn_detached(async move {
+                    let _ = future.await;
+                });
             }
-            let wf = bincode::deserialize_from(cvec.clone().as_slice()).unwrap();
-            Ok(wf)
-        })
+            Err(e) => {
+                let _ = sender.send(Err(e));
+            }
+        }
+        Subscription::new(receiver, abort_handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::service::Interceptor;
+
+    fn df_info(id: u32, path: &str) -> grpc_fs::DataFrameInfo {
+        grpc_fs::DataFrameInfo {
+            id: Some(grpc_fs::DataFrameId { id: id }),
+            df_path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn df_list_changes_are_found_by_comparing_lists() {
+        let mut known = std::collections::BTreeMap::new();
+        let first = grpc_fs::DataFrameInfoList {
+            list: vec![df_info(0, "/a.csv"), df_info(1, "/b.csv")],
+        };
+        assert_eq!(df_list_changes(&mut known, first.clone()).len(), 2);
+        assert!(df_list_changes(&mut known, first).is_empty());
+
+        let second = grpc_fs::DataFrameInfoList {
+            list: vec![df_info(1, "/c.csv"), df_info(2, "/d.csv")],
+        };
+        assert_eq!(
+            df_list_changes(&mut known, second),
+            vec![
+                DataFrameListChange::Removed(grpc_fs::DataFrameId { id: 0 }),
+                DataFrameListChange::Updated(df_info(1, "/c.csv")),
+                DataFrameListChange::Added(df_info(2, "/d.csv")),
+            ]
+        );
+    }
+
+    #[test]
+    fn streamed_list_changes_are_decoded() {
+        let removed = DataFrameListChangeMessage {
+            kind: DataFrameListChangeKind::Removed as i32,
+            info: Some(df_info(4, "")),
+        };
+        let decoded: DataFrameListChangeMessage =
+            prost::Message::decode(prost::Message::encode_to_vec(&removed).as_slice()).unwrap();
+        assert_eq!(
+            decoded.into_change().unwrap(),
+            DataFrameListChange::Removed(grpc_fs::DataFrameId { id: 4 })
+        );
+
+        let unknown = DataFrameListChangeMessage {
+            kind: 7,
+            info: Some(df_info(4, "/a.csv")),
+        };
+        assert!(unknown.into_change().is_err());
+    }
+
+    #[test]
+    fn streamed_world_frames_are_put_together_from_their_chunks() {
+        let payload = payload::encode(&h_analyzer_data::WorldFrame::new(3, 0.3));
+        let (first, second) = payload.split_at(payload.len() / 2);
+        let mut assembler = WorldFrameAssembler {
+            encoding: payload::Encoding::Identity,
+            bytes: Vec::new(),
+        };
+        let progress = TransferProgress::default();
+        let chunk = |data: &[u8], last| WorldFrameChunk {
+            frame_index: 3,
+            data: data.to_vec(),
+            last: last,
+        };
+        assert!(assembler
+            .push(chunk(first, false), &progress)
+            .unwrap()
+            .is_none());
+        let (frame_index, frame) = assembler
+            .push(chunk(second, true), &progress)
+            .unwrap()
+            .unwrap();
+        assert_eq!(frame_index, 3);
+        assert_eq!(frame.frame_index, 3);
+        assert!(assembler.bytes.is_empty());
+    }
+
+    #[test]
+    fn credentials_are_attached_as_metadata() {
+        let mut token = Credentials::BearerToken("abc".to_string());
+        let req = token.call(tonic::Request::new(())).unwrap();
+        assert_eq!(req.metadata().get("authorization").unwrap(), "Bearer abc");
+
+        let mut api_key = Credentials::ApiKey("xyz".to_string());
+        let req = api_key.call(tonic::Request::new(())).unwrap();
+        assert_eq!(req.metadata().get("x-api-key").unwrap(), "xyz");
+
+        let req = Credentials::None.call(tonic::Request::new(())).unwrap();
+        assert!(req.metadata().is_empty());
     }
 }
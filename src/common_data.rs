@@ -2,34 +2,251 @@ use std::borrow::BorrowMut;
 use std::collections::VecDeque;
 
 use crate::backend_talk::{self, grpc_data_transfer, grpc_fs};
-use crate::components::modal_window;
+use crate::components::{modal_window, notifications};
+use crate::csv_file;
+use crate::df_cache;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::local_backend;
+use crate::request::{RpcPromise, Subscription, TransferProgress, TransferStats};
+use crate::rpc_log;
+use crate::session;
 use crate::unwrap_or_continue;
 use polars::prelude::*;
-use poll_promise::Promise;
+
+/// Number of ui frames without any pane asking for a dataframe before its transfer is cancelled.
+const UNUSED_DF_CANCEL_FRAMES: u64 = 30;
+
+/// Failed health checks in a row after which a backend counts as disconnected.
+const DISCONNECTED_AFTER_FAILURES: u32 = 3;
+/// Health checks answered slower than this mark the backend as degraded.
+const SLOW_HEALTH_CHECK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Results of pushed down queries and files parsed in the frontend live here, their ids
+/// count down from this one so that they do not collide with the ones handed out by the server.
+const LOCAL_DF_ID_START: u32 = u32::MAX;
+
+/// A dataframe on one of the connected backends. Every server numbers its dataframes on its own,
+/// so an id only means something together with the index of its backend.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+pub struct DataFrameKey {
+    pub backend: usize,
+    pub id: usize,
+}
+
+impl DataFrameKey {
+    pub fn new(backend: usize, id: usize) -> Self {
+        Self {
+            backend: backend,
+            id: id,
+        }
+    }
+}
+
+/// A world on one of the connected backends.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub struct WorldKey {
+    pub backend: usize,
+    pub name: String,
+}
+
+/// A dataframe, or the part of it a pane needs.
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+pub struct DataFrameQuery {
+    pub df_id: DataFrameKey,
+    pub projection: backend_talk::DataFrameProjection,
+}
+
+impl DataFrameQuery {
+    pub fn whole(df_id: DataFrameKey) -> Self {
+        Self::new(df_id, backend_talk::DataFrameProjection::default())
+    }
+
+    pub fn new(df_id: DataFrameKey, projection: backend_talk::DataFrameProjection) -> Self {
+        Self {
+            df_id: df_id,
+            projection: projection,
+        }
+    }
+}
+
+/// A dataframe on its way from the backend.
+pub struct DataFrameTransfer {
+    pub promise: RpcPromise<backend_talk::FetchedDataFrame>,
+    pub progress: TransferProgress,
+}
+
+/// Backend for a connection profile, `local://` reads files of this machine without a server.
+fn open_backend(
+    (address, credentials): (String, backend_talk::Credentials),
+    rpc_log: &rpc_log::RpcLog,
+) -> Box<dyn backend_talk::Backend> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if address == local_backend::LOCAL_ADDRESS {
+        return Box::new(local_backend::LocalBackend::default());
+    }
+    Box::new(backend_talk::BackendTalk::new(
+        address,
+        credentials,
+        rpc_log.clone(),
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionState {
+    Connected,
+    /// health checks are slow or some of them failed
+    Degraded,
+    /// reachable, but the credentials of the profile are rejected
+    Unauthorized,
+    Disconnected,
+}
+
+/// One of the connected backends, with the listings requested from each of them.
+pub struct Connection {
+    pub backend: Box<dyn backend_talk::Backend>,
+    get_df_list_promise: Option<RpcPromise<grpc_fs::DataFrameInfoList>>,
+    world_list_promise: Option<RpcPromise<grpc_data_transfer::WorldMetadataList>>,
+    df_list_changes: Subscription<backend_talk::DataFrameListChange>,
+
+    pub state: ConnectionState,
+    health_promise: Option<(web_time::Instant, RpcPromise<grpc_fs::Empty>)>,
+    last_health_check: Option<web_time::Instant>,
+    failed_health_checks: u32,
+    // the dataframe list requested after a reconnect, compared against the one from before
+    resyncing: bool,
+}
+
+impl Connection {
+    fn new(backend: Box<dyn backend_talk::Backend>) -> Self {
+        let world_list_promise = backend.get_world_list();
+        let df_list_changes = backend.subscribe_df_list();
+        Self {
+            backend: backend,
+            get_df_list_promise: None,
+            world_list_promise: Some(world_list_promise),
+            df_list_changes: df_list_changes,
+            state: ConnectionState::Connected,
+            health_promise: None,
+            last_health_check: None,
+            failed_health_checks: 0,
+            resyncing: false,
+        }
+    }
+}
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct CommonData {
+    // dataframes and worlds are listed from all of them,
+    // the file browser and everything loaded through it work on the first one
     #[serde(skip)]
-    pub backend: backend_talk::BackendTalk,
+    pub backends: Vec<Connection>,
 
-    pub update_df_count: usize,
+    // request of the whole list, after the frontend changed it or on the user's demand.
+    // servers streaming list changes keep it up to date in between
     pub update_df_list: bool,
-    pub required_dataframes: std::collections::HashMap<usize, Option<DataFrame>>,
+    pub required_dataframes: std::collections::HashMap<DataFrameQuery, Option<DataFrame>>,
     pub latest_df_info_map:
-        std::collections::HashMap<usize, h_analyzer_data::grpc_fs::DataFrameInfo>,
-    pub just_added_df_id_opt: Option<usize>,
+        std::collections::HashMap<DataFrameKey, h_analyzer_data::grpc_fs::DataFrameInfo>,
+    pub just_added_df_id_opt: Option<DataFrameKey>,
+
+    // why a required dataframe could not be fetched, shown in the panes waiting for it
+    #[serde(skip)]
+    pub df_errors: std::collections::HashMap<DataFrameQuery, String>,
+    // dataframes of a backend which restarted, their ids are gone or belong to other files now
+    #[serde(skip)]
+    pub invalidated_df_ids: std::collections::HashSet<DataFrameKey>,
+    // seconds between health checks of every backend
+    pub health_check_interval: f32,
+    // ui frame in which a pane last asked for each dataframe
+    #[serde(skip)]
+    df_last_requested: std::collections::HashMap<DataFrameQuery, u64>,
+    #[serde(skip)]
+    frame_count: u64,
+    #[serde(skip)]
+    pub notifications: notifications::NotificationCenter,
+    // kept across reconnects, the calls leading up to one are often the interesting ones
+    #[serde(skip)]
+    pub rpc_log: rpc_log::RpcLog,
+
+    // with the worlds listed before each load, to tell which worlds the rosbag brought
+    #[serde(skip)]
+    load_rosbag_promises: Vec<(
+        session::SessionRosbag,
+        std::collections::HashSet<String>,
+        RpcPromise<grpc_fs::Empty>,
+    )>,
+    #[serde(skip)]
+    rosbag_world_lookups: Vec<(session::SessionRosbag, std::collections::HashSet<String>)>,
+    // rosbags loaded through the frontend, saved with the session
+    #[serde(skip)]
+    loaded_rosbags: Vec<session::SessionRosbag>,
+    // rosbags of a restored session waiting for the world list of their backend
+    #[serde(skip)]
+    session_rosbags: Vec<session::SessionRosbag>,
 
+    // dataframes of a restored session waiting for the list of their backend, and the ones being loaded again
+    #[serde(skip)]
+    session_dataframes: Vec<session::SessionDataFrame>,
     #[serde(skip)]
-    load_rosbag_promise: Option<Promise<Result<grpc_fs::Empty, tonic::Status>>>,
+    session_df_loads: Vec<(DataFrameKey, usize, RpcPromise<usize>)>,
+    // saved dataframes of the restored session by their saved id, and where each of them is now
+    #[serde(skip)]
+    restoring_dataframes: std::collections::HashMap<DataFrameKey, session::SessionDataFrame>,
+    #[serde(skip)]
+    df_id_remap: std::collections::HashMap<DataFrameKey, DataFrameKey>,
 
     #[serde(skip)]
-    pub get_df_list_promise:
-        Option<Promise<Result<h_analyzer_data::grpc_fs::DataFrameInfoList, tonic::Status>>>,
+    pub df_transfers: std::collections::HashMap<DataFrameQuery, DataFrameTransfer>,
+    // number of dataframes fetched in parallel
+    pub max_df_transfers: usize,
+    // fetched dataframes beyond this are dropped, least recently shown first
+    pub df_cache_budget_mib: usize,
+    // entries of the persistent cache beyond this are removed, least recently used first
+    pub disk_cache_budget_mib: usize,
+    // dataframes of earlier sessions, shown right away and revalidated with the backend
+    #[serde(skip)]
+    df_cache: df_cache::DataFrameCache,
+    #[serde(skip)]
+    df_cache_lookups: std::collections::HashMap<
+        DataFrameQuery,
+        (
+            df_cache::CacheKey,
+            poll_promise::Promise<Option<df_cache::CachedDataFrame>>,
+        ),
+    >,
+    // queries not found in the local cache, fetched from the backend as usual
     #[serde(skip)]
-    pub get_df_promise: Option<(usize, Promise<Result<DataFrame, tonic::Status>>)>,
+    df_cache_misses: std::collections::HashSet<DataFrameQuery>,
+    // size and compression of the last completed transfer of each dataframe
     #[serde(skip)]
-    pub get_df_from_file_promise: Option<Promise<Result<usize, tonic::Status>>>,
+    pub df_transfer_stats: std::collections::HashMap<DataFrameKey, TransferStats>,
+    #[serde(skip)]
+    pub get_df_from_file_promise: Option<RpcPromise<usize>>,
+    // path and parsed contents of a file of this machine, read off the ui thread
+    #[serde(skip)]
+    local_file_promise: Option<(String, poll_promise::Promise<Result<DataFrame, String>>)>,
+    // results of queries evaluated by a backend and files parsed in the frontend,
+    // listed in `latest_df_info_map` next to the dataframes of that backend
+    #[serde(skip)]
+    local_dataframes: std::collections::BTreeMap<DataFrameKey, (grpc_fs::DataFrameInfo, DataFrame)>,
+    // backend of the queried dataframe, title and result
+    #[serde(skip)]
+    lazy_query_promises: Vec<(usize, String, RpcPromise<DataFrame>)>,
 
     pub modal_window_input_opt: Option<modal_window::ModalWindowInput>,
 
@@ -37,102 +254,494 @@ pub struct CommonData {
     pub default_path: String,
 
     #[serde(skip)]
-    pub world_list_promise:
-        Option<Promise<Result<grpc_data_transfer::WorldMetadataList, tonic::Status>>>,
-
-    #[serde(skip)]
-    pub world_frame_promise: Option<Promise<Result<h_analyzer_data::WorldFrame, tonic::Status>>>,
+    pub world_frame_promise: Option<RpcPromise<Vec<h_analyzer_data::WorldFrame>>>,
     #[serde(skip)]
     pub world: h_analyzer_data::World,
     #[serde(skip)]
-    pub world_name: String,
+    pub world_key: WorldKey,
     pub world_playing: bool,
+    // frames loaded ahead of the current one, and the most frames fetched by a single request
+    pub world_prefetch_window: usize,
+    pub world_batch_size: usize,
+    // subscribe to new frames and always show the latest one instead of polling frame by frame
+    pub follow_latest: bool,
+    #[serde(skip)]
+    world_subscription: Option<(WorldKey, Subscription<h_analyzer_data::WorldFrame>)>,
 
     #[serde(skip)]
     series_list_req_time: web_time::Instant,
     pub sl_time_history: std::collections::VecDeque<f64>,
 
     #[serde(skip)]
-    pub save_df_list_promise: Option<Promise<Result<backend_talk::grpc_fs::Empty, tonic::Status>>>,
+    pub save_df_list_promises: Vec<RpcPromise<backend_talk::grpc_fs::Empty>>,
     #[serde(skip)]
-    pub fs_list_promise:
-        Option<Promise<Result<backend_talk::grpc_fs::ListResponse, tonic::Status>>>,
+    pub fs_list_promise: Option<RpcPromise<backend_talk::grpc_fs::ListResponse>>,
     #[serde(skip)]
-    pub load_df_promise: Option<(String, Promise<Result<DataFrame, tonic::Status>>)>,
+    pub load_df_promise: Option<(String, RpcPromise<DataFrame>)>,
     #[serde(skip)]
-    pub d_path_promise: Option<Promise<Result<backend_talk::grpc_fs::PathMessage, tonic::Status>>>,
+    pub d_path_promise: Option<RpcPromise<backend_talk::grpc_fs::PathMessage>>,
 }
 
 impl Default for CommonData {
     fn default() -> Self {
+        Self::new(vec![(
+            backend_talk::DEFAULT_SERVER_ADDRESS.to_string(),
+            backend_talk::Credentials::None,
+        )])
+    }
+}
+
+impl CommonData {
+    /// Connect to each `(server address, credentials)` pair, the first one is the primary backend.
+    pub fn new(endpoints: Vec<(String, backend_talk::Credentials)>) -> Self {
+        Self::with_rpc_log(endpoints, rpc_log::RpcLog::default())
+    }
+
+    fn with_rpc_log(
+        endpoints: Vec<(String, backend_talk::Credentials)>,
+        rpc_log: rpc_log::RpcLog,
+    ) -> Self {
+        let backends = endpoints
+            .into_iter()
+            .map(|endpoint| open_backend(endpoint, &rpc_log))
+            .collect();
+        let mut cdata = Self::with_backends(backends);
+        cdata.rpc_log = rpc_log;
+        cdata.df_cache =
+            df_cache::DataFrameCache::persistent().with_budget(cdata.disk_cache_budget());
+        cdata
+    }
+
+    pub fn with_backends(backends: Vec<Box<dyn backend_talk::Backend>>) -> Self {
+        let mut backends: Vec<Connection> = backends.into_iter().map(Connection::new).collect();
+        if backends.is_empty() {
+            backends.push(Connection::new(Box::new(backend_talk::BackendTalk::new(
+                backend_talk::DEFAULT_SERVER_ADDRESS.to_string(),
+                backend_talk::Credentials::None,
+                rpc_log::RpcLog::default(),
+            ))));
+        }
         let path = "/".to_string();
-        let backend = backend_talk::BackendTalk::default();
-        let fs_list_promise = backend.request_list(path.clone());
-        let d_path_promise = backend.request_default_path();
-        let wl_promise = backend.get_world_list();
+        let fs_list_promise = backends[0].backend.request_list(path.clone());
+        let d_path_promise = backends[0].backend.request_default_path();
         Self {
-            backend: backend,
+            backends: backends,
 
-            update_df_count: 0,
             update_df_list: true,
             required_dataframes: std::collections::HashMap::new(),
-            get_df_list_promise: None,
-            get_df_promise: None,
+            df_transfers: std::collections::HashMap::new(),
+            max_df_transfers: 4,
+            df_cache_budget_mib: 512,
+            disk_cache_budget_mib: 512,
+            df_cache: df_cache::DataFrameCache::default(),
+            df_cache_lookups: std::collections::HashMap::new(),
+            df_cache_misses: std::collections::HashSet::new(),
+            df_transfer_stats: std::collections::HashMap::new(),
             get_df_from_file_promise: None,
+            local_file_promise: None,
+            local_dataframes: std::collections::BTreeMap::new(),
+            lazy_query_promises: Vec::new(),
             latest_df_info_map: std::collections::HashMap::new(),
             just_added_df_id_opt: None,
+            df_errors: std::collections::HashMap::new(),
+            invalidated_df_ids: std::collections::HashSet::new(),
+            health_check_interval: 2.0,
+            df_last_requested: std::collections::HashMap::new(),
+            frame_count: 0,
+            notifications: notifications::NotificationCenter::default(),
+            rpc_log: rpc_log::RpcLog::default(),
 
-            load_rosbag_promise: None,
+            load_rosbag_promises: Vec::new(),
+            rosbag_world_lookups: Vec::new(),
+            loaded_rosbags: Vec::new(),
+            session_rosbags: Vec::new(),
+
+            session_dataframes: Vec::new(),
+            session_df_loads: Vec::new(),
+            restoring_dataframes: std::collections::HashMap::new(),
+            df_id_remap: std::collections::HashMap::new(),
 
             modal_window_input_opt: None,
             current_path: path.clone(),
             default_path: path.clone(),
 
-            world_list_promise: Some(wl_promise),
             world_frame_promise: None,
             world: h_analyzer_data::World::new(),
-            world_name: "".to_string(),
+            world_key: WorldKey::default(),
             world_playing: true,
+            world_prefetch_window: 100,
+            world_batch_size: 20,
+            follow_latest: false,
+            world_subscription: None,
 
             series_list_req_time: web_time::Instant::now(),
             sl_time_history: std::collections::VecDeque::new(),
 
-            save_df_list_promise: None,
+            save_df_list_promises: Vec::new(),
             fs_list_promise: Some(fs_list_promise),
             load_df_promise: None,
             d_path_promise: Some(d_path_promise),
         }
     }
-}
 
-impl CommonData {
+    /// Switch to other backends. Everything cached from the previous servers is dropped,
+    /// since dataframe ids and world histories are only meaningful per server.
+    pub fn connect(&mut self, endpoints: Vec<(String, backend_talk::Credentials)>) {
+        log::info!(
+            "connecting to {:?}",
+            endpoints
+                .iter()
+                .map(|(address, _)| address)
+                .collect::<Vec<_>>()
+        );
+        let world_playing = self.world_playing;
+        let follow_latest = self.follow_latest;
+        let world_prefetch_window = self.world_prefetch_window;
+        let world_batch_size = self.world_batch_size;
+        let max_df_transfers = self.max_df_transfers;
+        let health_check_interval = self.health_check_interval;
+        let df_cache_budget_mib = self.df_cache_budget_mib;
+        let disk_cache_budget_mib = self.disk_cache_budget_mib;
+        *self = Self::with_rpc_log(endpoints, self.rpc_log.clone());
+        self.world_playing = world_playing;
+        self.follow_latest = follow_latest;
+        self.world_prefetch_window = world_prefetch_window;
+        self.world_batch_size = world_batch_size;
+        self.max_df_transfers = max_df_transfers;
+        self.health_check_interval = health_check_interval;
+        self.df_cache_budget_mib = df_cache_budget_mib;
+        self.disk_cache_budget_mib = disk_cache_budget_mib;
+        self.apply_disk_cache_budget();
+    }
+
+    fn disk_cache_budget(&self) -> u64 {
+        self.disk_cache_budget_mib as u64 * 1024 * 1024
+    }
+
+    /// Have the persistent cache keep to `disk_cache_budget_mib` from the next stored entry on.
+    pub fn apply_disk_cache_budget(&mut self) {
+        self.df_cache = self.df_cache.clone().with_budget(self.disk_cache_budget());
+    }
+
+    /// Server address and state of every backend.
+    pub fn connection_states(&self) -> Vec<(&str, ConnectionState)> {
+        self.backends
+            .iter()
+            .map(|connection| (connection.backend.server_address(), connection.state))
+            .collect()
+    }
+
+    pub fn server_address(&self, backend: usize) -> Option<&str> {
+        Some(self.backends.get(backend)?.backend.server_address())
+    }
+
+    /// The backend browsed by the file explorer.
+    pub fn primary_backend(&self) -> &dyn backend_talk::Backend {
+        self.backends[0].backend.as_ref()
+    }
+
     pub fn load_rosbag2(&mut self, dirpath: String) {
-        self.load_rosbag_promise = Some(self.backend.load_rosbag2(dirpath));
+        self.load_rosbag_on(0, dirpath);
+    }
+
+    fn load_rosbag_on(&mut self, backend: usize, dirpath: String) {
+        let connection = match self.backends.get(backend) {
+            Some(connection) => connection,
+            None => return,
+        };
+        let rosbag = session::SessionRosbag {
+            server_address: connection.backend.server_address().to_string(),
+            dirpath: dirpath.clone(),
+            worlds: Vec::new(),
+        };
+        let promise = connection.backend.load_rosbag2(dirpath);
+        let listed_before = self.listed_worlds(backend).unwrap_or_default();
+        self.load_rosbag_promises
+            .push((rosbag, listed_before, promise));
     }
 
     pub fn update_world_list(&mut self) {
-        self.world_list_promise = Some(self.backend.get_world_list());
+        for connection in self.backends.iter_mut() {
+            connection.world_list_promise = Some(connection.backend.get_world_list());
+        }
     }
 
-    pub fn get_world_list(&mut self) -> Option<grpc_data_transfer::WorldMetadataList> {
-        if let Some(world_list_promise) = &self.world_list_promise {
-            if let Ok(world_list) = world_list_promise.ready()? {
-                let ret = world_list.clone();
-                return Some(ret);
+    /// Worlds of every backend that has answered, with the index of the backend.
+    pub fn get_world_lists(&self) -> Vec<(usize, grpc_data_transfer::WorldMetadataList)> {
+        let mut world_lists = Vec::new();
+        for (backend, connection) in self.backends.iter().enumerate() {
+            let promise = unwrap_or_continue!(connection.world_list_promise.as_ref());
+            if let Some(Ok(world_list)) = promise.ready() {
+                world_lists.push((backend, world_list.clone()));
             }
         }
-        None
+        world_lists
+    }
+
+    /// Names of the worlds on `backend`, once it has answered.
+    fn listed_worlds(&self, backend: usize) -> Option<std::collections::HashSet<String>> {
+        let promise = self.backends.get(backend)?.world_list_promise.as_ref()?;
+        let world_list = promise.ready()?.as_ref().ok()?;
+        Some(
+            world_list
+                .list
+                .iter()
+                .filter_map(|world_meta| Some(world_meta.id.as_ref()?.id.clone()))
+                .collect(),
+        )
+    }
+
+    /// Number of frames the backend of `world` reported for it.
+    pub fn world_frame_num(&self, world: &WorldKey) -> u32 {
+        let world_lists = self.get_world_lists();
+        let world_list = world_lists
+            .iter()
+            .find(|(backend, _)| *backend == world.backend);
+        match world_list {
+            Some((_, world_list)) => world_list
+                .list
+                .iter()
+                .filter(|world_meta| world_meta.id.clone().unwrap().id == world.name)
+                .map(|world_meta| world_meta.total_frame_num)
+                .max()
+                .unwrap_or(0),
+            None => 0,
+        }
     }
 
+    /// Have every server save the list of its dataframes.
     pub fn save_df_list(&mut self) {
-        //self.save_df_list_promise = Some(self.backend.save_df_list(dfi_list));
+        for (backend, connection) in self.backends.iter().enumerate() {
+            if connection.backend.is_in_process() {
+                continue;
+            }
+            let df_info_list = self
+                .latest_df_info_map
+                .iter()
+                .filter(|(key, _)| {
+                    key.backend == backend && !self.local_dataframes.contains_key(key)
+                })
+                .map(|(_, info)| info.clone())
+                .collect();
+            self.save_df_list_promises
+                .push(connection.backend.save_df_list(df_info_list));
+        }
+    }
+
+    fn backend_index(&self, server_address: &str) -> Option<usize> {
+        self.backends
+            .iter()
+            .position(|connection| connection.backend.server_address() == server_address)
+    }
+
+    /// Files and rosbags loaded on the backends, to be restored on the next start.
+    /// Query results, files parsed in the frontend and the preview not yet confirmed are not part of it.
+    pub fn session(&self, selected_world: Option<&WorldKey>) -> session::Session {
+        let mut dataframes: Vec<session::SessionDataFrame> = self
+            .latest_df_info_map
+            .iter()
+            .filter(|(key, _)| !self.local_dataframes.contains_key(key))
+            .filter(|(key, _)| Some(**key) != self.just_added_df_id_opt)
+            .filter_map(|(key, info)| {
+                Some(session::SessionDataFrame {
+                    server_address: self.server_address(key.backend)?.to_string(),
+                    df_id: *key,
+                    df_path: info.df_path.clone(),
+                    load_option: info.load_option.clone().unwrap_or_default(),
+                })
+            })
+            .collect();
+        dataframes.sort_by_key(|df| df.df_id);
+        // dataframes still being restored are not listed yet, but belong to the session all the same
+        dataframes.extend(self.session_dataframes.iter().cloned());
+        let selected_world = selected_world.and_then(|world| {
+            Some(session::SessionWorld {
+                server_address: self.server_address(world.backend)?.to_string(),
+                name: world.name.clone(),
+            })
+        });
+        session::Session {
+            dataframes: dataframes,
+            rosbags: self
+                .loaded_rosbags
+                .iter()
+                .chain(self.session_rosbags.iter())
+                .cloned()
+                .collect(),
+            selected_world: selected_world,
+        }
+    }
+
+    /// Load the rosbags and files of `session` again, on the backends with the same address.
+    /// Files and rosbags still loaded there are not loaded twice. Returns the world to select.
+    pub fn restore_session(&mut self, session: session::Session) -> Option<WorldKey> {
+        for rosbag in session.rosbags {
+            if self.backend_index(&rosbag.server_address).is_none() {
+                log::warn!(
+                    "{} of the last session is not connected",
+                    rosbag.server_address
+                );
+                continue;
+            }
+            self.session_rosbags.push(rosbag);
+        }
+        for df in session.dataframes {
+            if self.backend_index(&df.server_address).is_none() {
+                log::warn!("{} of the last session is not connected", df.server_address);
+                continue;
+            }
+            self.restoring_dataframes.insert(df.df_id, df.clone());
+            self.session_dataframes.push(df);
+        }
+        let world = session.selected_world?;
+        Some(WorldKey {
+            backend: self.backend_index(&world.server_address)?,
+            name: world.name,
+        })
+    }
+
+    /// Sources of a workspace made elsewhere, with the ones on servers not connected here moved to
+    /// the primary backend. Files are found again by their path on it.
+    pub fn retarget_session(&self, mut session: session::Session) -> session::Session {
+        let primary = self.primary_backend().server_address().to_string();
+        for df in session.dataframes.iter_mut() {
+            if self.backend_index(&df.server_address).is_none() {
+                df.server_address = primary.clone();
+            }
+        }
+        for rosbag in session.rosbags.iter_mut() {
+            if self.backend_index(&rosbag.server_address).is_none() {
+                rosbag.server_address = primary.clone();
+            }
+        }
+        if let Some(world) = session.selected_world.as_mut() {
+            if self.backend_index(&world.server_address).is_none() {
+                world.server_address = primary;
+            }
+        }
+        session
+    }
+
+    /// The id is from the restored session and may name another dataframe now. Panes showing it get
+    /// the cached copy of the saved file, nothing is fetched for it until the file is back.
+    pub fn is_restoring(&self, df_id: DataFrameKey) -> bool {
+        self.restoring_dataframes.contains_key(&df_id)
+    }
+
+    /// Once the session is restored, where each saved dataframe id went.
+    /// Panes have to replace the ids they refer to, all at once since old and new ids overlap.
+    pub fn take_df_id_remap(&mut self) -> std::collections::HashMap<DataFrameKey, DataFrameKey> {
+        let restored = self.session_dataframes.is_empty() && self.session_df_loads.is_empty();
+        if !restored || self.restoring_dataframes.is_empty() {
+            return std::collections::HashMap::new();
+        }
+        // cached copies shown under the saved ids, which may name other dataframes from now on
+        let restoring = std::mem::take(&mut self.restoring_dataframes);
+        let saved = |query: &DataFrameQuery| !restoring.contains_key(&query.df_id);
+        self.required_dataframes.retain(|query, _| saved(query));
+        self.df_cache_lookups.retain(|query, _| saved(query));
+        self.df_cache_misses.retain(|query| saved(query));
+        self.df_errors.retain(|query, _| saved(query));
+        self.df_last_requested.retain(|query, _| saved(query));
+        std::mem::take(&mut self.df_id_remap)
+    }
+
+    fn continue_session_restore(&mut self) {
+        // only rosbags whose worlds are gone from the list are loaded again
+        for rosbag in std::mem::take(&mut self.session_rosbags) {
+            let backend = unwrap_or_continue!(self.backend_index(&rosbag.server_address));
+            let connection = &self.backends[backend];
+            let listing = connection
+                .world_list_promise
+                .as_ref()
+                .map_or(false, |promise| promise.ready().is_none());
+            if listing || connection.state == ConnectionState::Disconnected {
+                self.session_rosbags.push(rosbag);
+                continue;
+            }
+            let still_loaded = self
+                .listed_worlds(backend)
+                .map_or(false, |worlds| rosbag.is_listed_in(&worlds));
+            if still_loaded {
+                self.loaded_rosbags.push(rosbag);
+            } else {
+                self.load_rosbag_on(backend, rosbag.dirpath);
+            }
+        }
+
+        let mut waiting = Vec::new();
+        let mut loading = false;
+        for df in std::mem::take(&mut self.session_dataframes) {
+            let backend = unwrap_or_continue!(self.backend_index(&df.server_address));
+            let connection = &self.backends[backend];
+            // only files missing from the list are loaded again
+            let listing = self.update_df_list || connection.get_df_list_promise.is_some();
+            if listing || connection.state == ConnectionState::Disconnected {
+                waiting.push(df);
+                continue;
+            }
+            let listed = self
+                .latest_df_info_map
+                .iter()
+                .filter(|(key, _)| {
+                    key.backend == backend && !self.local_dataframes.contains_key(key)
+                })
+                .find(|(_, info)| df.is_loaded_as(info));
+            match listed {
+                Some((key, _)) => {
+                    self.df_id_remap.insert(df.df_id, *key);
+                }
+                None => {
+                    let promise = connection
+                        .backend
+                        .load_df_from_file_request(df.df_path.clone(), df.load_option.clone());
+                    self.session_df_loads.push((df.df_id, backend, promise));
+                    loading = true;
+                }
+            }
+        }
+        self.session_dataframes = waiting;
+        if loading {
+            self.update_df_list = true;
+        }
+
+        let mut finished = Vec::new();
+        self.session_df_loads
+            .retain(|(df_id, backend, promise)| match promise.ready() {
+                Some(result) => {
+                    finished.push((*df_id, *backend, result.clone()));
+                    false
+                }
+                None => true,
+            });
+        for (df_id, backend, result) in finished {
+            match result {
+                Ok(id) => {
+                    self.df_id_remap
+                        .insert(df_id, DataFrameKey::new(backend, id));
+                    self.update_df_list = true;
+                }
+                Err(e) => {
+                    self.notifications.push("load_df_from_file_request", &e);
+                    // panes showing it are asked to select another one
+                    self.invalidated_df_ids.insert(df_id);
+                }
+            }
+        }
     }
 
     pub fn remove_preview_data_frame(&mut self) {
         if let Some(df_id) = self.just_added_df_id_opt {
-            let _ = self
-                .backend
-                .remove_df_request(h_analyzer_data::grpc_fs::DataFrameId { id: df_id as u32 });
+            if self.local_dataframes.remove(&df_id).is_some() {
+                self.latest_df_info_map.remove(&df_id);
+                self.just_added_df_id_opt = None;
+                return;
+            }
+            let _ =
+                self.primary_backend()
+                    .remove_df_request(h_analyzer_data::grpc_fs::DataFrameId {
+                        id: df_id.id as u32,
+                    });
             self.just_added_df_id_opt = None;
             self.update_df_list = true;
         }
@@ -144,142 +753,1031 @@ impl CommonData {
         load_option: &h_analyzer_data::grpc_fs::DataFrameLoadOption,
     ) {
         self.get_df_from_file_promise = Some(
-            self.backend
+            self.primary_backend()
                 .load_df_from_file_request(filepath.clone(), load_option.clone()),
         );
         self.update_df_list = true;
     }
 
-    pub fn request_df_transmission(&mut self, df_id: usize) {
+    /// Parse a file of the user's machine instead of having the backend load it.
+    pub fn load_local_file(
+        &mut self,
+        filepath: &String,
+        local_file: modal_window::LocalFile,
+        load_option: &h_analyzer_data::grpc_fs::DataFrameLoadOption,
+    ) {
+        match local_file {
+            modal_window::LocalFile::Contents(contents) => {
+                self.load_data_frame_from_bytes(filepath, &contents, load_option)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            modal_window::LocalFile::Path(path) => {
+                let load_option = load_option.clone();
+                // large files would freeze the ui while being read and parsed
+                let promise = poll_promise::Promise::spawn_thread("read_local_file", move || {
+                    let contents = std::fs::read(&path).map_err(|e| e.to_string())?;
+                    csv_file::read_csv(&contents, &load_option).map_err(|e| e.to_string())
+                });
+                self.local_file_promise = Some((filepath.clone(), promise));
+            }
+        }
+    }
+
+    /// Parse a file handed over by the browser instead of having the backend load it.
+    pub fn load_data_frame_from_bytes(
+        &mut self,
+        filepath: &String,
+        contents: &[u8],
+        load_option: &h_analyzer_data::grpc_fs::DataFrameLoadOption,
+    ) {
+        match csv_file::read_csv(contents, load_option) {
+            Ok(df) => {
+                let df_id = self.add_local_df(0, filepath.clone(), df);
+                self.just_added_df_id_opt = Some(df_id);
+            }
+            Err(e) => self.notifications.push(
+                "load_df_from_file_request",
+                &tonic::Status::invalid_argument(e.to_string()),
+            ),
+        }
+    }
+
+    pub fn request_df_transmission(&mut self, df_id: DataFrameKey) {
+        self.request_df_query(DataFrameQuery::whole(df_id));
+    }
+
+    /// Ask for `query` to be fetched. Panes call this every frame they show the result.
+    pub fn request_df_query(&mut self, query: DataFrameQuery) {
+        self.df_last_requested
+            .insert(query.clone(), self.frame_count);
         let rdf = self.required_dataframes.borrow_mut();
-        if rdf.get(&df_id).is_none() && !rdf.contains_key(&df_id) {
-            rdf.insert(df_id, None);
+        if !rdf.contains_key(&query) {
+            rdf.insert(query, None);
+        }
+    }
+
+    pub fn required_df(&mut self, query: &DataFrameQuery) -> Option<&mut DataFrame> {
+        self.required_dataframes.get_mut(query)?.as_mut()
+    }
+
+    /// Bytes taken by the fetched dataframes and the ones only held in the frontend, and their number.
+    /// Parts of the latter share their memory, so they are not counted again.
+    pub fn df_cache_usage(&self) -> (usize, usize) {
+        let fetched = self
+            .required_dataframes
+            .iter()
+            .filter(|(query, _)| !self.local_dataframes.contains_key(&query.df_id))
+            .filter_map(|(_, df)| df.as_ref());
+        let local = self.local_dataframes.values().map(|(_, df)| df);
+        fetched.chain(local).fold((0, 0), |(bytes, count), df| {
+            (bytes + df.estimated_size(), count + 1)
+        })
+    }
+
+    /// Drop fetched dataframes until they fit into the budget, least recently shown first.
+    /// Dataframes shown in the last frame are kept, panes would only fetch them again.
+    /// Parsed files and query results cannot be fetched again, so they only go once
+    /// the fetched ones are not enough, and the user is told about it.
+    fn enforce_df_cache_budget(&mut self) {
+        let budget = self.df_cache_budget_mib * 1024 * 1024;
+        let (mut used, _) = self.df_cache_usage();
+        if used <= budget {
+            return;
+        }
+        let mut evictable: Vec<(u64, DataFrameQuery, usize)> = self
+            .required_dataframes
+            .iter()
+            .filter(|(query, _)| !self.local_dataframes.contains_key(&query.df_id))
+            .filter_map(|(query, df)| {
+                let size = df.as_ref()?.estimated_size();
+                let last_requested = self.df_last_requested.get(query).copied().unwrap_or(0);
+                if last_requested + 1 < self.frame_count {
+                    Some((last_requested, query.clone(), size))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        evictable.sort();
+        for (_, query, size) in evictable {
+            if used <= budget {
+                break;
+            }
+            log::info!("evict {:?} from the dataframe cache", query);
+            self.required_dataframes.remove(&query);
+            used -= size;
+        }
+        if used <= budget {
+            return;
+        }
+
+        let mut local: Vec<(u64, std::cmp::Reverse<usize>, DataFrameKey, usize)> = self
+            .local_dataframes
+            .iter()
+            .filter(|(df_id, _)| Some(**df_id) != self.just_added_df_id_opt)
+            .filter_map(|(df_id, (_, df))| {
+                let last_shown = self
+                    .df_last_requested
+                    .iter()
+                    .filter(|(query, _)| query.df_id == *df_id)
+                    .map(|(_, frame)| *frame)
+                    .max()
+                    .unwrap_or(0);
+                if last_shown + 1 < self.frame_count {
+                    // ids count down, so older ones go first among the ones never shown
+                    Some((
+                        last_shown,
+                        std::cmp::Reverse(df_id.id),
+                        *df_id,
+                        df.estimated_size(),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        local.sort();
+        for (_, _, df_id, size) in local {
+            if used <= budget {
+                break;
+            }
+            let (info, _) = unwrap_or_continue!(self.local_dataframes.remove(&df_id));
+            self.latest_df_info_map.remove(&df_id);
+            self.required_dataframes
+                .retain(|query, _| query.df_id != df_id);
+            self.invalidated_df_ids.insert(df_id);
+            self.notifications.push(
+                "df_cache",
+                &tonic::Status::resource_exhausted(format!(
+                    "{} was dropped to stay within the cache budget",
+                    info.df_path
+                )),
+            );
+            used -= size;
+        }
+    }
+
+    /// Key of `query` in the local cache. Local files and frontend results are not cached.
+    /// Ids of the restored session are keyed by the saved file, which needs no list from the backend.
+    fn df_cache_key(&self, query: &DataFrameQuery) -> Option<df_cache::CacheKey> {
+        let connection = self.backends.get(query.df_id.backend)?;
+        if connection.backend.is_in_process() {
+            return None;
+        }
+        if let Some(saved) = self.restoring_dataframes.get(&query.df_id) {
+            let df_info = grpc_fs::DataFrameInfo {
+                df_path: saved.df_path.clone(),
+                load_option: Some(saved.load_option.clone()),
+                ..Default::default()
+            };
+            return Some(df_cache::CacheKey::new(
+                &saved.server_address,
+                &df_info,
+                &query.projection,
+            ));
+        }
+        if self.local_dataframes.contains_key(&query.df_id) {
+            return None;
+        }
+        Some(df_cache::CacheKey::new(
+            connection.backend.server_address(),
+            self.latest_df_info_map.get(&query.df_id)?,
+            &query.projection,
+        ))
+    }
+
+    /// Look up required dataframes in the local cache before fetching them. Cached ones are shown
+    /// right away and only fetched again if the backend reports a different fingerprint.
+    fn lookup_cached_dataframes(&mut self) {
+        let lookups: Vec<(DataFrameQuery, df_cache::CacheKey)> = self
+            .required_dataframes
+            .iter()
+            .filter(|(query, df)| {
+                df.is_none()
+                    && !self.df_transfers.contains_key(*query)
+                    && !self.df_cache_lookups.contains_key(*query)
+                    && !self.df_cache_misses.contains(*query)
+            })
+            .filter_map(|(query, _)| Some((query.clone(), self.df_cache_key(query)?)))
+            .collect();
+        for (query, key) in lookups {
+            let promise = self.df_cache.load(&key);
+            self.df_cache_lookups.insert(query, (key, promise));
+        }
+
+        let finished: Vec<DataFrameQuery> = self
+            .df_cache_lookups
+            .iter()
+            .filter(|(_, (_, promise))| promise.ready().is_some())
+            .map(|(query, _)| query.clone())
+            .collect();
+        for query in finished {
+            let (_, promise) = unwrap_or_continue!(self.df_cache_lookups.remove(&query));
+            // ready, so this does not block
+            let cached = match promise.block_and_take() {
+                Some(cached) => cached,
+                None => {
+                    self.df_cache_misses.insert(query);
+                    continue;
+                }
+            };
+            let rdf = unwrap_or_continue!(self.required_dataframes.get_mut(&query));
+            *rdf = Some(cached.df);
+            // revalidated under the new id once the file is loaded again
+            if self.is_restoring(query.df_id) {
+                continue;
+            }
+            let reachable = self
+                .backends
+                .get(query.df_id.backend)
+                .map_or(false, |connection| {
+                    connection.state == ConnectionState::Connected
+                        || connection.state == ConnectionState::Degraded
+                });
+            if !reachable {
+                continue;
+            }
+            match self.backend_projects(&query) {
+                Some(true) => self.start_df_transfer(query, Some(cached.fingerprint)),
+                // revalidated along with the whole dataframe it is sliced from
+                Some(false) => self.request_df_query(DataFrameQuery::whole(query.df_id)),
+                None => {}
+            }
+        }
+    }
+
+    /// Fetch `query` from its backend, unless its content still has the fingerprint `if_none_match`.
+    fn start_df_transfer(&mut self, query: DataFrameQuery, if_none_match: Option<String>) {
+        let connection = match self.backends.get(query.df_id.backend) {
+            Some(connection) => connection,
+            None => {
+                self.df_errors
+                    .insert(query, "backend is not connected".to_string());
+                return;
+            }
+        };
+        let progress = TransferProgress::default();
+        let transfer = DataFrameTransfer {
+            promise: connection.backend.get_df_request(
+                h_analyzer_data::grpc_fs::DataFrameId {
+                    id: query.df_id.id as u32,
+                },
+                query.projection.clone(),
+                if_none_match,
+                progress.clone(),
+            ),
+            progress: progress,
+        };
+        self.df_transfers.insert(query, transfer);
+    }
+
+    pub fn df_transfer_progress(&self, query: &DataFrameQuery) -> Option<TransferStats> {
+        self.df_transfers
+            .get(query)
+            .map(|transfer| transfer.progress.stats())
+    }
+
+    /// Running transfers of any part of `df_id`.
+    pub fn df_transfers_of(&self, df_id: DataFrameKey) -> Vec<(DataFrameQuery, TransferStats)> {
+        let mut transfers: Vec<(DataFrameQuery, TransferStats)> = self
+            .df_transfers
+            .iter()
+            .filter(|(query, _)| query.df_id == df_id)
+            .map(|(query, transfer)| (query.clone(), transfer.progress.stats()))
+            .collect();
+        transfers.sort_by(|a, b| a.0.cmp(&b.0));
+        transfers
+    }
+
+    /// Abort the transfer of `query`. It is not requested again until `retry_df_transmission`.
+    pub fn cancel_df_transmission(&mut self, query: &DataFrameQuery) {
+        if let Some(transfer) = self.df_transfers.remove(query) {
+            log::info!("transfer of {:?} cancelled", query);
+            transfer.promise.cancel();
+            self.df_errors
+                .insert(query.clone(), "transfer cancelled".to_string());
+        }
+    }
+
+    /// Forget the failure of `query` so that it is requested again.
+    pub fn retry_df_transmission(&mut self, query: &DataFrameQuery) {
+        self.df_errors.remove(query);
+        if self.backend_projects(query) == Some(false) {
+            self.df_errors.remove(&DataFrameQuery::whole(query.df_id));
+        }
+    }
+
+    /// Whether the backend of `query` cuts out the part asked for, `None` until it has told.
+    /// Whole dataframes and query results need nothing cut out by a backend.
+    fn backend_projects(&self, query: &DataFrameQuery) -> Option<bool> {
+        if query.projection.is_whole() || self.local_dataframes.contains_key(&query.df_id) {
+            return Some(true);
+        }
+        self.backends
+            .get(query.df_id.backend)?
+            .backend
+            .supports_projection()
+    }
+
+    /// Parts of dataframes on backends which cannot cut them out are sliced from the whole
+    /// dataframe, which is fetched once instead of sending it for every part.
+    fn slice_from_whole_dataframes(&mut self) {
+        let sliced: Vec<DataFrameQuery> = self
+            .required_dataframes
+            .iter()
+            .filter(|(query, df)| {
+                df.is_none()
+                    && !self.df_cache_lookups.contains_key(*query)
+                    && self.backend_projects(query) == Some(false)
+            })
+            .map(|(query, _)| query.clone())
+            .collect();
+        for query in sliced {
+            let whole = DataFrameQuery::whole(query.df_id);
+            self.request_df_query(whole.clone());
+            if let Some(e) = self.df_errors.get(&whole).cloned() {
+                self.df_errors.insert(query, e);
+                continue;
+            }
+            let df = unwrap_or_continue!(self.required_dataframes.get(&whole).cloned().flatten());
+            match query.projection.apply(df) {
+                Ok(df) => {
+                    self.required_dataframes.insert(query, Some(df));
+                }
+                Err(e) => {
+                    self.df_errors.insert(query, e.to_string());
+                }
+            }
         }
     }
 
     pub fn get_just_loaded_data_frame(&mut self) -> Option<DataFrame> {
-        if let Ok(id) = self.get_df_from_file_promise.as_ref()?.ready()? {
-            self.just_added_df_id_opt = Some(id.clone());
+        if let Some((filepath, promise)) = self.local_file_promise.take() {
+            match promise.try_take() {
+                Ok(Ok(df)) => {
+                    let df_id = self.add_local_df(0, filepath, df);
+                    self.just_added_df_id_opt = Some(df_id);
+                }
+                Ok(Err(e)) => self.notifications.push(
+                    "load_df_from_file_request",
+                    &tonic::Status::invalid_argument(e),
+                ),
+                Err(promise) => self.local_file_promise = Some((filepath, promise)),
+            }
+        }
+        if let Some(loaded) = self
+            .get_df_from_file_promise
+            .as_ref()
+            .and_then(|p| p.ready())
+        {
+            match loaded {
+                Ok(id) => {
+                    self.just_added_df_id_opt = Some(DataFrameKey::new(0, *id));
+                    // the list requested along with the load may predate the new dataframe
+                    self.update_df_list = true;
+                }
+                Err(e) => self.notifications.push("load_df_from_file_request", e),
+            }
             self.get_df_from_file_promise = None;
         }
         if let Some(just_added_df_id) = self.just_added_df_id_opt {
-            self.request_df_transmission(just_added_df_id);
-            self.required_dataframes.get(&just_added_df_id)?.clone()
+            let query = DataFrameQuery::whole(just_added_df_id);
+            self.request_df_query(query.clone());
+            self.required_dataframes.get(&query)?.clone()
         } else {
             None
         }
     }
 
-    pub fn update(&mut self, selected_world_name: Option<String>) {
-        if self.update_df_count > 50 {
-            self.update_df_list = true;
-            self.update_df_count = 0;
-        } else {
-            self.update_df_count += 1;
+    pub fn is_local_df(&self, df_id: DataFrameKey) -> bool {
+        self.local_dataframes.contains_key(&df_id)
+    }
+
+    /// The data of `df_id` is in this process, either as a query result or on a local backend.
+    fn is_in_process(&self, df_id: DataFrameKey) -> bool {
+        self.is_local_df(df_id)
+            || self
+                .backends
+                .get(df_id.backend)
+                .map_or(false, |connection| connection.backend.is_in_process())
+    }
+
+    /// Something to build a lazy query on. For a dataframe on the server this is an empty frame
+    /// with its schema, which the server replaces by the data when evaluating the plan.
+    /// `None` until the schema has arrived. Fails if it cannot be fetched, or only along with the
+    /// whole dataframe from a server which cannot cut out anything.
+    pub fn lazy_source(&mut self, df_id: DataFrameKey) -> Result<Option<LazyFrame>, String> {
+        if let Some((_, df)) = self.local_dataframes.get(&df_id) {
+            return Ok(Some(df.clone().lazy()));
+        }
+        if self.is_in_process(df_id) {
+            let query = DataFrameQuery::whole(df_id);
+            self.request_df_query(query.clone());
+            return Ok(self.required_df(&query).map(|df| df.clone().lazy()));
+        }
+        // a whole dataframe shown anyway has the schema as well
+        let whole = DataFrameQuery::whole(df_id);
+        if let Some(Some(df)) = self.required_dataframes.get(&whole) {
+            return Ok(Some(df.clear().lazy()));
+        }
+        let query = DataFrameQuery::new(df_id, backend_talk::DataFrameProjection::schema());
+        match self.backend_projects(&query) {
+            Some(true) => {}
+            // the schema alone would mean downloading everything the query is meant to spare
+            Some(false) => {
+                return Err(
+                    "the server cannot send the schema alone, so it cannot run queries".to_string(),
+                )
+            }
+            None => return Ok(None),
+        }
+        if let Some(e) = self.df_errors.remove(&query) {
+            // asked for again on the next attempt
+            self.required_dataframes.remove(&query);
+            return Err(format!("failed to fetch the schema: {}", e));
+        }
+        self.request_df_query(query.clone());
+        Ok(self.required_df(&query).map(|df| df.clone().lazy()))
+    }
+
+    /// List `df`, computed by a pane from `source`, as a new dataframe which other panes can show.
+    pub fn add_derived_df(
+        &mut self,
+        source: DataFrameKey,
+        title: &str,
+        df: DataFrame,
+    ) -> DataFrameKey {
+        let df_path = match self.latest_df_info_map.get(&source) {
+            Some(info) => format!("{}: {}", title, info.df_path),
+            None => title.to_string(),
+        };
+        self.add_local_df(source.backend, df_path, df)
+    }
+
+    /// Evaluate `plan` built on `lazy_source(df_id)` and list the result as a new dataframe.
+    pub fn push_down_query(&mut self, df_id: DataFrameKey, title: String, plan: LazyFrame) {
+        if self.is_in_process(df_id) {
+            // nothing to push down, the data is here already
+            match plan.collect() {
+                Ok(df) => {
+                    self.add_local_df(df_id.backend, format!("query: {}", title), df);
+                }
+                Err(e) => self.notifications.push(
+                    "run_lazy_query",
+                    &tonic::Status::invalid_argument(e.to_string()),
+                ),
+            }
+            return;
+        }
+        let plan = match serde_json::to_string(&plan.logical_plan) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.notifications.push(
+                    "run_lazy_query",
+                    &tonic::Status::invalid_argument(e.to_string()),
+                );
+                return;
+            }
+        };
+        let connection = match self.backends.get(df_id.backend) {
+            Some(connection) => connection,
+            None => return,
+        };
+        let promise = connection.backend.run_lazy_query(
+            h_analyzer_data::grpc_fs::DataFrameId {
+                id: df_id.id as u32,
+            },
+            plan,
+            TransferProgress::default(),
+        );
+        self.lazy_query_promises
+            .push((df_id.backend, title, promise));
+    }
+
+    /// `df` is listed with the dataframes of `backend`, where it was computed or loaded for.
+    fn add_local_df(&mut self, backend: usize, df_path: String, df: DataFrame) -> DataFrameKey {
+        let id = self
+            .local_dataframes
+            .keys()
+            .map(|key| key.id as u32 - 1)
+            .min()
+            .unwrap_or(LOCAL_DF_ID_START);
+        let info = grpc_fs::DataFrameInfo {
+            id: Some(grpc_fs::DataFrameId { id: id }),
+            df_path: df_path,
+            ..Default::default()
+        };
+        let key = DataFrameKey::new(backend, id as usize);
+        self.latest_df_info_map.insert(key, info.clone());
+        self.local_dataframes.insert(key, (info, df));
+        key
+    }
+
+    fn append_world_frame(&mut self, wf: h_analyzer_data::WorldFrame) {
+        if let Some(lwf) = self.world.history.last() {
+            if lwf.frame_index > wf.frame_index {
+                // loop detected
+                self.world.reset();
+            }
+        }
+        self.world.history.push(wf);
+    }
+
+    fn follow_world(&mut self, world_key: WorldKey) {
+        let subscribed = matches!(&self.world_subscription, Some((key, _)) if *key == world_key);
+        if !subscribed {
+            let connection = match self.backends.get(world_key.backend) {
+                Some(connection) => connection,
+                None => return,
+            };
+            let subscription = connection
+                .backend
+                .subscribe_world_frames(world_key.name.clone(), self.world.history.len() as u32);
+            self.world_subscription = Some((world_key.clone(), subscription));
+            self.world_key = world_key;
+        }
+
+        let received = match &self.world_subscription {
+            Some((_, subscription)) => subscription.drain(),
+            None => Vec::new(),
+        };
+        let connected = self
+            .backends
+            .get(self.world_key.backend)
+            .map_or(false, |connection| {
+                connection.state == ConnectionState::Connected
+            });
+        for wf in received {
+            match wf {
+                Ok(wf) => self.append_world_frame(wf),
+                // health checks already tell about backends which are down
+                Err(e) if connected => self.notifications.push("subscribe_world_frames", &e),
+                Err(_) => {}
+            }
+        }
+        if !self.world.history.is_empty() {
+            self.world.current_index = self.world.history.len() - 1;
+        }
+    }
+
+    /// Ping every backend now and then. Once one comes back after being disconnected,
+    /// everything listed from it is requested again.
+    fn check_health(&mut self) {
+        let interval = std::time::Duration::from_secs_f32(self.health_check_interval.max(0.0));
+        let mut reconnected = Vec::new();
+        for (backend, connection) in self.backends.iter_mut().enumerate() {
+            let due = connection
+                .last_health_check
+                .map_or(true, |last| last.elapsed() >= interval);
+            if connection.health_promise.is_none() && due {
+                connection.health_promise =
+                    Some((web_time::Instant::now(), connection.backend.check_health()));
+            }
+            let (sent, promise) = unwrap_or_continue!(connection.health_promise.as_ref());
+            let result = unwrap_or_continue!(promise.ready());
+            let previous = connection.state;
+            match result {
+                Ok(_) => {
+                    connection.failed_health_checks = 0;
+                    connection.state = if sent.elapsed() > SLOW_HEALTH_CHECK {
+                        ConnectionState::Degraded
+                    } else {
+                        ConnectionState::Connected
+                    };
+                    // lists requested while the credentials were rejected failed as well
+                    if previous == ConnectionState::Disconnected
+                        || previous == ConnectionState::Unauthorized
+                    {
+                        reconnected.push(backend);
+                    }
+                }
+                // the server answered, retrying will not help until the credentials are changed
+                Err(e)
+                    if e.code() == tonic::Code::Unauthenticated
+                        || e.code() == tonic::Code::PermissionDenied =>
+                {
+                    connection.failed_health_checks = 0;
+                    connection.state = ConnectionState::Unauthorized;
+                    if previous != ConnectionState::Unauthorized {
+                        self.notifications.push("check_health", e);
+                    }
+                }
+                Err(e) => {
+                    connection.failed_health_checks += 1;
+                    if connection.failed_health_checks >= DISCONNECTED_AFTER_FAILURES {
+                        connection.state = ConnectionState::Disconnected;
+                        if previous != ConnectionState::Disconnected {
+                            self.notifications.push("check_health", e);
+                        }
+                    } else {
+                        connection.state = ConnectionState::Degraded;
+                    }
+                }
+            }
+            connection.health_promise = None;
+            connection.last_health_check = Some(web_time::Instant::now());
+        }
+        for backend in reconnected {
+            self.resync(backend);
+        }
+    }
+
+    /// Request everything listed from `backend` again, it may have restarted in the meantime.
+    fn resync(&mut self, backend: usize) {
+        log::info!(
+            "backend {} reconnected, requesting its state again",
+            backend
+        );
+        let connection = match self.backends.get_mut(backend) {
+            Some(connection) => connection,
+            None => return,
+        };
+        connection.get_df_list_promise = Some(connection.backend.request_get_df_list());
+        connection.world_list_promise = Some(connection.backend.get_world_list());
+        // the change stream broke off along with the connection
+        connection.df_list_changes = connection.backend.subscribe_df_list();
+        connection.resyncing = true;
+        if backend == 0 {
+            self.d_path_promise = Some(connection.backend.request_default_path());
+        }
+        // the world of a restarted server starts over, and a running subscription has given up on it
+        if self.world_key.backend == backend {
+            self.world.reset();
+            self.world_subscription = None;
+            if let Some(wf_promise) = self.world_frame_promise.take() {
+                wf_promise.cancel();
+            }
         }
+        // cached dataframes may be outdated, panes fetch them again on their next frame
+        self.required_dataframes.retain(|query, _| {
+            query.df_id.backend != backend || self.local_dataframes.contains_key(&query.df_id)
+        });
+        let stale: Vec<DataFrameQuery> = self
+            .df_transfers
+            .keys()
+            .filter(|query| query.df_id.backend == backend)
+            .cloned()
+            .collect();
+        for query in stale {
+            if let Some(transfer) = self.df_transfers.remove(&query) {
+                transfer.promise.cancel();
+            }
+        }
+        self.df_errors
+            .retain(|query, _| query.df_id.backend != backend);
+    }
+
+    /// Ids of `backend` which no longer name the dataframe they named before it was resynced.
+    fn invalidated_by_restart(
+        &self,
+        backend: usize,
+        latest_df_list: &grpc_fs::DataFrameInfoList,
+    ) -> Vec<DataFrameKey> {
+        self.latest_df_info_map
+            .iter()
+            .filter(|(key, _)| key.backend == backend && !self.local_dataframes.contains_key(key))
+            .filter(|(key, info)| {
+                !latest_df_list.list.iter().any(|latest| {
+                    latest.id.as_ref().map(|id| id.id as usize) == Some(key.id)
+                        && latest.df_path == info.df_path
+                })
+            })
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    /// Keep `latest_df_info_map` in sync with the change streams of the backends.
+    fn apply_df_list_changes(&mut self) {
+        for backend in 0..self.backends.len() {
+            for change in self.backends[backend].df_list_changes.drain() {
+                match change {
+                    Ok(change) => self.apply_df_list_change(backend, change),
+                    // without the stream, the list is refreshed whenever the frontend changed it
+                    Err(e) if e.code() == tonic::Code::Unimplemented => log::info!(
+                        "{} does not stream dataframe list changes",
+                        self.backends[backend].backend.server_address()
+                    ),
+                    // health checks already tell about backends which are down
+                    Err(e) => {
+                        if self.backends[backend].state == ConnectionState::Connected {
+                            self.notifications.push("watch_df_list", &e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Only dataframes which changed are dropped from the cache, and fetched again by the panes
+    /// showing them.
+    fn apply_df_list_change(&mut self, backend: usize, change: backend_talk::DataFrameListChange) {
+        let listed_id = |info: &grpc_fs::DataFrameInfo| {
+            let id = info.id.as_ref()?.id as usize;
+            Some(DataFrameKey::new(backend, id))
+        };
+        let (df_id, info) = match change {
+            backend_talk::DataFrameListChange::Added(info) => {
+                let df_id = match listed_id(&info) {
+                    Some(df_id) => df_id,
+                    None => return,
+                };
+                (df_id, Some(info))
+            }
+            backend_talk::DataFrameListChange::Updated(info) => {
+                let df_id = match listed_id(&info) {
+                    Some(df_id) => df_id,
+                    None => return,
+                };
+                let renamed = self
+                    .latest_df_info_map
+                    .get(&df_id)
+                    .map_or(false, |previous| previous.df_path != info.df_path);
+                if renamed {
+                    self.invalidated_df_ids.insert(df_id);
+                }
+                self.required_dataframes
+                    .retain(|query, _| query.df_id != df_id);
+                (df_id, Some(info))
+            }
+            backend_talk::DataFrameListChange::Removed(id) => {
+                let df_id = DataFrameKey::new(backend, id.id as usize);
+                if self.latest_df_info_map.contains_key(&df_id) {
+                    self.invalidated_df_ids.insert(df_id);
+                }
+                self.required_dataframes
+                    .retain(|query, _| query.df_id != df_id);
+                (df_id, None)
+            }
+        };
+        match info {
+            Some(info) => {
+                self.latest_df_info_map.insert(df_id, info);
+            }
+            None => {
+                self.latest_df_info_map.remove(&df_id);
+            }
+        }
+    }
+
+    pub fn update(&mut self, selected_world: Option<WorldKey>) {
+        self.frame_count += 1;
+        self.check_health();
+        self.apply_df_list_changes();
         // retrieve dataframe list update if needed
         if self.update_df_list {
-            if self.get_df_list_promise.is_none() {
-                self.get_df_list_promise = Some(self.backend.request_get_df_list());
-            }
-            if let Some(get_df_list) = &self.get_df_list_promise {
-                if let Some(get_df_list) = get_df_list.ready() {
-                    if let Ok(latest_df_list) = get_df_list {
-                        self.latest_df_info_map.clear();
-                        for df_info in latest_df_list.list.iter() {
-                            self.latest_df_info_map
-                                .insert(df_info.clone().id.unwrap().id as usize, df_info.clone());
-                            log::info!("{:?}", df_info);
-                            if df_info.load_option.as_ref().unwrap().updated {
-                                self.required_dataframes
-                                    .remove(&(df_info.id.as_ref().unwrap().id as usize));
-                            }
+            for connection in self.backends.iter_mut() {
+                // listed again once it is back, until then the last list is shown
+                if connection.state == ConnectionState::Disconnected {
+                    continue;
+                }
+                if connection.get_df_list_promise.is_none() {
+                    connection.get_df_list_promise = Some(connection.backend.request_get_df_list());
+                }
+            }
+            self.update_df_list = false;
+        }
+        // a refreshed list is compared against the entries of its backend, so that only the
+        // dataframes which changed are dropped, like for the change stream
+        for backend in 0..self.backends.len() {
+            let connection = &self.backends[backend];
+            let get_df_list = match &connection.get_df_list_promise {
+                Some(promise) => unwrap_or_continue!(promise.ready()).clone(),
+                None => continue,
+            };
+            match get_df_list {
+                Ok(latest_df_list) => {
+                    if connection.resyncing {
+                        let invalidated = self.invalidated_by_restart(backend, &latest_df_list);
+                        if !invalidated.is_empty() {
+                            self.notifications.push(
+                                "request_get_df_list",
+                                &tonic::Status::not_found(format!(
+                                    "{} restarted, {} of its dataframes are gone or have new ids",
+                                    connection.backend.server_address(),
+                                    invalidated.len()
+                                )),
+                            );
                         }
+                        self.invalidated_df_ids.extend(invalidated);
+                    }
+                    let mut known: std::collections::BTreeMap<u32, grpc_fs::DataFrameInfo> = self
+                        .latest_df_info_map
+                        .iter()
+                        .filter(|(key, _)| {
+                            key.backend == backend && !self.local_dataframes.contains_key(key)
+                        })
+                        .map(|(key, info)| (key.id as u32, info.clone()))
+                        .collect();
+                    for change in backend_talk::df_list_changes(&mut known, latest_df_list) {
+                        log::info!("{:?}", change);
+                        self.apply_df_list_change(backend, change);
                     }
-                    self.update_df_list = false;
-                    self.get_df_list_promise = None;
                 }
+                Err(e) => self.notifications.push("request_get_df_list", &e),
             }
+            let connection = &mut self.backends[backend];
+            connection.get_df_list_promise = None;
+            connection.resyncing = false;
         }
+        self.continue_session_restore();
 
-        // request sending required dataframe from backend
-        for required_df in self.required_dataframes.iter() {
-            let id = *required_df.0;
-            if required_df.1.is_none() && self.get_df_promise.is_none() {
-                self.get_df_promise = Some((
-                    id,
-                    self.backend
-                        .get_df_request(h_analyzer_data::grpc_fs::DataFrameId { id: id as u32 }),
-                ));
+        // give up on transfers once no pane has shown interest in them for a while
+        let unused: Vec<DataFrameQuery> = self
+            .df_transfers
+            .iter()
+            .filter(|(query, transfer)| {
+                let last_requested = self.df_last_requested.get(*query).copied().unwrap_or(0);
+                self.frame_count - last_requested > UNUSED_DF_CANCEL_FRAMES
+                    && transfer.promise.ready().is_none()
+            })
+            .map(|(query, _)| query.clone())
+            .collect();
+        for query in unused {
+            log::info!("cancel transfer of unused {:?}", query);
+            if let Some(transfer) = self.df_transfers.remove(&query) {
+                transfer.promise.cancel();
+            }
+            self.required_dataframes.remove(&query);
+        }
+
+        self.slice_from_whole_dataframes();
+        self.lookup_cached_dataframes();
+
+        // request sending required dataframes from backend.
+        // panes only ask for dataframes while they are drawn, so the most recently requested ones are visible
+        let mut pending: Vec<DataFrameQuery> = self
+            .required_dataframes
+            .iter()
+            .filter(|(query, df)| {
+                df.is_none()
+                    && !self.df_transfers.contains_key(*query)
+                    && !self.df_errors.contains_key(*query)
+                    && !self.df_cache_lookups.contains_key(*query)
+                    && !self.is_restoring(query.df_id)
+                    && self.backend_projects(query) == Some(true)
+            })
+            .map(|(query, _)| query.clone())
+            .collect();
+        // query results are already here, panes only need their part of them
+        pending.retain(|query| {
+            let (_, df) = match self.local_dataframes.get(&query.df_id) {
+                Some(local) => local,
+                None => return true,
+            };
+            match query.projection.apply(df.clone()) {
+                Ok(df) => {
+                    self.required_dataframes.insert(query.clone(), Some(df));
+                }
+                Err(e) => {
+                    self.df_errors.insert(query.clone(), e.to_string());
+                }
+            }
+            false
+        });
+        pending.sort_by_key(|query| {
+            (
+                std::cmp::Reverse(self.df_last_requested.get(query).copied().unwrap_or(0)),
+                query.clone(),
+            )
+        });
+        let free_slots = self
+            .max_df_transfers
+            .max(1)
+            .saturating_sub(self.df_transfers.len());
+        for query in pending.into_iter().take(free_slots) {
+            self.start_df_transfer(query, None);
+        }
+
+        // check which dataframe requests have completed
+        let finished: Vec<DataFrameQuery> = self
+            .df_transfers
+            .iter()
+            .filter(|(_, transfer)| transfer.promise.ready().is_some())
+            .map(|(query, _)| query.clone())
+            .collect();
+        for query in finished {
+            let transfer = unwrap_or_continue!(self.df_transfers.remove(&query));
+            match transfer.promise.ready() {
+                Some(Ok(fetched)) => {
+                    self.df_transfer_stats
+                        .insert(query.df_id, transfer.progress.stats());
+                    // without a dataframe, the cached copy is still current
+                    if let Some(requested_df) = &fetched.df {
+                        if let Some(rdf) = self.required_dataframes.get_mut(&query) {
+                            *rdf = Some(requested_df.clone());
+                        }
+                        let cache_key = self.df_cache_key(&query);
+                        if let (Some(key), Some(fingerprint)) = (cache_key, &fetched.fingerprint) {
+                            self.df_cache.store(
+                                key,
+                                df_cache::CachedDataFrame {
+                                    fingerprint: fingerprint.clone(),
+                                    df: requested_df.clone(),
+                                },
+                            );
+                            self.df_cache_misses.remove(&query);
+                        }
+                    }
+                    self.df_errors.remove(&query);
+                }
+                Some(Err(e)) => {
+                    self.notifications.push("get_df_request", e);
+                    // a cached copy stays shown while it cannot be revalidated
+                    let cached = matches!(self.required_dataframes.get(&query), Some(Some(_)));
+                    if !cached {
+                        self.df_errors
+                            .insert(query, format!("{:?}: {}", e.code(), e.message()));
+                    }
+                }
+                None => {}
             }
         }
 
-        // check if the dataframe request has completed
-        if let Some(get_df_promise) = &self.get_df_promise {
-            let requested_df_id = get_df_promise.0;
-            if let Some(requested_df) = get_df_promise.1.ready() {
-                if let Ok(requested_df) = requested_df {
-                    *self.required_dataframes.get_mut(&requested_df_id).unwrap() =
-                        Some(requested_df.clone());
+        self.enforce_df_cache_budget();
+
+        let mut finished_queries = Vec::new();
+        self.lazy_query_promises
+            .retain(|(backend, title, promise)| match promise.ready() {
+                Some(result) => {
+                    finished_queries.push((*backend, title.clone(), result.clone()));
+                    false
+                }
+                None => true,
+            });
+        for (backend, title, result) in finished_queries {
+            match result {
+                Ok(df) => {
+                    self.add_local_df(backend, format!("query: {}", title), df);
                 }
-                self.get_df_promise = None;
+                Err(e) => self.notifications.push("run_lazy_query", &e),
             }
         }
 
         // world frame update
-        let selected_world_name = if let Some(name) = selected_world_name {
-            name
+        let selected_world = if let Some(key) = selected_world {
+            key
         } else {
-            "slam".to_string()
+            WorldKey {
+                backend: 0,
+                name: "slam".to_string(),
+            }
         };
-        if selected_world_name != self.world_name {
+        if selected_world != self.world_key {
             self.world.reset();
         }
         if self.world_playing {
             self.world.next();
         }
-        if self.world_frame_promise.is_none() {
-            let mut w_f_num = 0;
-            if let Some(world_list) = self.get_world_list() {
-                for world_meta in world_list.list.iter() {
-                    let wname = world_meta.id.clone().unwrap().id;
-                    if wname == selected_world_name {
-                        w_f_num = world_meta.total_frame_num;
-                    }
-                }
+        if self.follow_latest {
+            if let Some(wf_promise) = self.world_frame_promise.take() {
+                wf_promise.cancel();
             }
+            self.follow_world(selected_world.clone());
+        } else {
+            self.world_subscription = None;
+        }
+        if self.world_frame_promise.is_none() && !self.follow_latest {
+            let w_f_num = self.world_frame_num(&selected_world);
             log::debug!(
                 "total frame num: {}, current: {}",
                 w_f_num,
                 self.world.history.len()
             );
-            if self.world.history.len() < w_f_num as usize {
+            // only frames within the prefetch window ahead of the current one are loaded
+            let loaded = self.world.history.len();
+            let wanted = std::cmp::min(
+                w_f_num as usize,
+                self.world.current_index + self.world_prefetch_window + 1,
+            );
+            let connection = self.backends.get(selected_world.backend);
+            if let Some(connection) = connection.filter(|_| loaded < wanted) {
+                let count = std::cmp::min(wanted - loaded, self.world_batch_size.max(1));
                 self.series_list_req_time = web_time::Instant::now();
-                self.world_frame_promise =
-                    Some(self.backend.get_world_frame(
-                        selected_world_name.clone(),
-                        self.world.history.len() as u32,
-                    ));
-                self.world_name = selected_world_name.clone();
+                self.world_frame_promise = Some(connection.backend.get_world_frames(
+                    selected_world.name.clone(),
+                    loaded as u32,
+                    count as u32,
+                ));
+                self.world_key = selected_world.clone();
             }
         }
         if let Some(wf_promise) = &self.world_frame_promise {
             if let Some(wf) = wf_promise.ready() {
-                if let Ok(wf) = wf {
-                    //log::info!("world frame {}", wf);
-                    if let Some(lwf) = self.world.history.last() {
-                        if lwf.frame_index > wf.frame_index {
-                            // loop detected
-                            self.world.reset();
+                match wf {
+                    Ok(wfs) => {
+                        //log::info!("world frame {}", wf);
+                        let wfs = wfs.clone();
+                        for wf in wfs {
+                            self.append_world_frame(wf);
                         }
-                    }
-                    self.world.history.push(wf.clone());
 
-                    let et = self.series_list_req_time.elapsed().as_nanos() as f64;
-                    self.sl_time_history.push_back(et * 1e-9);
-                    if self.sl_time_history.len() > 10 {
-                        self.sl_time_history.pop_front();
+                        let et = self.series_list_req_time.elapsed().as_nanos() as f64;
+                        self.sl_time_history.push_back(et * 1e-9);
+                        if self.sl_time_history.len() > 10 {
+                            self.sl_time_history.pop_front();
+                        }
                     }
+                    Err(e) => self.notifications.push("get_world_frames", e),
                 }
                 self.world_frame_promise = None;
             }
@@ -289,15 +1787,851 @@ impl CommonData {
         if let Some(d_path_promise) = &self.d_path_promise {
             if let Some(d_path) = d_path_promise.ready() {
                 log::info!("d_path_promise: {:?}", d_path);
-                if let Ok(d_path) = d_path.as_ref() {
-                    let d_path = d_path.path.clone();
-                    self.current_path = d_path.clone();
-                    self.default_path = d_path.clone();
-                    self.fs_list_promise = Some(self.backend.request_list(d_path.clone()));
+                match d_path.as_ref() {
+                    Ok(d_path) => {
+                        let d_path = d_path.path.clone();
+                        self.current_path = d_path.clone();
+                        self.default_path = d_path.clone();
+                        self.fs_list_promise =
+                            Some(self.primary_backend().request_list(d_path.clone()));
+                    }
+                    Err(e) => self.notifications.push("request_default_path", e),
                 }
 
                 self.d_path_promise = None;
             }
         }
+
+        // the promises below are kept around as caches by their views, so they are only dropped on failure
+        for connection in self.backends.iter_mut() {
+            if let Some(Err(e)) = connection
+                .world_list_promise
+                .as_ref()
+                .and_then(|p| p.ready())
+            {
+                self.notifications.push("get_world_list", e);
+                connection.world_list_promise = None;
+            }
+        }
+        if let Some(Err(e)) = self.fs_list_promise.as_ref().and_then(|p| p.ready()) {
+            self.notifications.push("request_list", e);
+            self.fs_list_promise = None;
+        }
+        let mut loaded_rosbags = Vec::new();
+        self.load_rosbag_promises
+            .retain(|(rosbag, listed_before, promise)| match promise.ready() {
+                Some(result) => {
+                    loaded_rosbags.push((rosbag.clone(), listed_before.clone(), result.clone()));
+                    false
+                }
+                None => true,
+            });
+        for (rosbag, listed_before, result) in loaded_rosbags {
+            match result {
+                Ok(_) => {
+                    // the worlds it brought show up in the next list
+                    if let Some(backend) = self.backend_index(&rosbag.server_address) {
+                        let connection = &mut self.backends[backend];
+                        connection.world_list_promise = Some(connection.backend.get_world_list());
+                    }
+                    self.rosbag_world_lookups.push((rosbag, listed_before));
+                }
+                Err(e) => self.notifications.push("load_rosbag2", &e),
+            }
+        }
+        for (mut rosbag, listed_before) in std::mem::take(&mut self.rosbag_world_lookups) {
+            let backend = unwrap_or_continue!(self.backend_index(&rosbag.server_address));
+            let listing = self.backends[backend]
+                .world_list_promise
+                .as_ref()
+                .map_or(false, |promise| promise.ready().is_none());
+            if listing {
+                self.rosbag_world_lookups.push((rosbag, listed_before));
+                continue;
+            }
+            // without a list, it is loaded again with the next session
+            let mut worlds: Vec<String> = self
+                .listed_worlds(backend)
+                .unwrap_or_default()
+                .difference(&listed_before)
+                .cloned()
+                .collect();
+            worlds.sort();
+            rosbag.worlds = worlds;
+            self.loaded_rosbags
+                .retain(|loaded| !loaded.is_same_rosbag(&rosbag));
+            self.loaded_rosbags.push(rosbag);
+        }
+        let mut saved = Vec::new();
+        self.save_df_list_promises
+            .retain(|promise| match promise.ready() {
+                Some(result) => {
+                    saved.push(result.clone());
+                    false
+                }
+                None => true,
+            });
+        for result in saved {
+            if let Err(e) = result {
+                self.notifications.push("save_df_list", &e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend_talk::Backend;
+    use crate::fake_backend::FakeBackend;
+
+    fn key(id: usize) -> DataFrameKey {
+        DataFrameKey::new(0, id)
+    }
+
+    fn with_fake(fake: &FakeBackend) -> CommonData {
+        let backend: Box<dyn Backend> = Box::new(fake.clone());
+        CommonData::with_backends(vec![backend])
+    }
+
+    fn world_frame(frame_index: usize) -> h_analyzer_data::WorldFrame {
+        h_analyzer_data::WorldFrame::new(frame_index, frame_index as f64 * 0.1)
+    }
+
+    #[test]
+    fn df_list_refresh_fills_info_map() {
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0, 2.0]).unwrap(), false);
+        fake.add_dataframe(3, "/data/b.csv", df!("y" => [3.0]).unwrap(), false);
+        let mut cdata = with_fake(&fake);
+
+        cdata.update(None);
+
+        assert!(!cdata.update_df_list);
+        assert_eq!(cdata.latest_df_info_map.len(), 2);
+        assert_eq!(cdata.latest_df_info_map[&key(3)].df_path, "/data/b.csv");
+    }
+
+    #[test]
+    fn dataframes_and_worlds_of_several_backends_are_kept_apart() {
+        let onboard = FakeBackend::default();
+        let workstation = FakeBackend::default();
+        onboard.add_dataframe(0, "/robot/trj.csv", df!("x" => [1.0]).unwrap(), false);
+        workstation.add_dataframe(0, "/replay/trj.csv", df!("x" => [1.0, 2.0]).unwrap(), false);
+        onboard.push_world_frame("slam", world_frame(0));
+        for i in 0..2 {
+            workstation.push_world_frame("slam", world_frame(i));
+        }
+        let mut cdata = CommonData::with_backends(vec![
+            Box::new(onboard.clone()) as Box<dyn Backend>,
+            Box::new(workstation.clone()) as Box<dyn Backend>,
+        ]);
+
+        let replayed = DataFrameKey::new(1, 0);
+        cdata.request_df_transmission(replayed);
+        cdata.update(Some(WorldKey {
+            backend: 1,
+            name: "slam".to_string(),
+        }));
+
+        assert_eq!(cdata.latest_df_info_map.len(), 2);
+        assert_eq!(
+            cdata.latest_df_info_map[&replayed].df_path,
+            "/replay/trj.csv"
+        );
+        let df = cdata.required_df(&DataFrameQuery::whole(replayed)).unwrap();
+        assert_eq!(df.shape(), (2, 1));
+        assert!(onboard.state().requested_df_ids.is_empty());
+        assert_eq!(workstation.state().requested_df_ids, vec![0]);
+
+        assert_eq!(cdata.world.history.len(), 2);
+        assert!(onboard.state().requested_frames.is_empty());
+    }
+
+    #[test]
+    fn required_dataframe_is_fetched_and_refetched_when_updated() {
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0, 2.0]).unwrap(), false);
+        let mut cdata = with_fake(&fake);
+
+        let query = DataFrameQuery::whole(key(0));
+        cdata.request_df_transmission(key(0));
+        cdata.update(None);
+        let df = cdata.required_dataframes[&query].as_ref().unwrap();
+        assert_eq!(df.shape(), (2, 1));
+        assert_eq!(fake.state().requested_df_ids, vec![0]);
+
+        // the server reloaded the file and reports it on the change stream, so the cached copy has to go
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0, 2.0, 3.0]).unwrap(), true);
+        cdata.update(None);
+        assert!(!cdata.required_dataframes.contains_key(&query));
+
+        cdata.request_df_transmission(key(0));
+        cdata.update(None);
+        let df = cdata.required_dataframes[&query].as_ref().unwrap();
+        assert_eq!(df.shape(), (3, 1));
+        assert_eq!(fake.state().requested_df_ids, vec![0, 0]);
+    }
+
+    #[test]
+    fn change_stream_only_evicts_changed_dataframes() {
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0]).unwrap(), false);
+        fake.add_dataframe(1, "/data/b.csv", df!("y" => [2.0]).unwrap(), false);
+        let mut cdata = with_fake(&fake);
+        cdata.request_df_transmission(key(0));
+        cdata.request_df_transmission(key(1));
+        cdata.update(None);
+
+        fake.add_dataframe(1, "/data/b.csv", df!("y" => [2.0, 3.0]).unwrap(), true);
+        fake.remove_df_request(grpc_fs::DataFrameId { id: 0 });
+        cdata.update(None);
+
+        assert!(!cdata.latest_df_info_map.contains_key(&key(0)));
+        assert!(cdata.invalidated_df_ids.contains(&key(0)));
+        assert!(!cdata.invalidated_df_ids.contains(&key(1)));
+        assert!(!cdata
+            .required_dataframes
+            .contains_key(&DataFrameQuery::whole(key(1))));
+    }
+
+    #[test]
+    fn least_recently_shown_dataframes_are_evicted_beyond_budget() {
+        let fake = FakeBackend::default();
+        let big = DataFrame::new(vec![Series::new("x", vec![0.0f64; 400_000])]).unwrap();
+        for id in 0..3 {
+            fake.add_dataframe(id, "/data/big.csv", big.clone(), false);
+        }
+        let mut cdata = with_fake(&fake);
+        cdata.df_cache_budget_mib = 7;
+
+        for id in 0..3 {
+            cdata.request_df_transmission(key(id));
+            cdata.update(None);
+        }
+        // only the dataframe shown last is still in view
+        cdata.request_df_transmission(key(2));
+        cdata.update(None);
+
+        let (used, count) = cdata.df_cache_usage();
+        assert_eq!(count, 2);
+        assert!(used <= 7 * 1024 * 1024);
+        assert!(!cdata
+            .required_dataframes
+            .contains_key(&DataFrameQuery::whole(key(0))));
+
+        // asked for again, it is fetched again
+        cdata.request_df_transmission(key(0));
+        cdata.update(None);
+        assert!(cdata.required_df(&DataFrameQuery::whole(key(0))).is_some());
+        assert_eq!(fake.state().requested_df_ids, vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn list_is_refreshed_on_demand_without_change_stream() {
+        let fake = FakeBackend::default();
+        fake.state().without_change_stream = true;
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0]).unwrap(), false);
+        fake.add_dataframe(1, "/data/b.csv", df!("y" => [2.0]).unwrap(), false);
+        let mut cdata = with_fake(&fake);
+        cdata.request_df_transmission(key(0));
+        cdata.request_df_transmission(key(1));
+        cdata.update(None);
+        assert_eq!(cdata.latest_df_info_map.len(), 2);
+
+        // nothing is noticed until the list is asked for again
+        fake.add_dataframe(1, "/data/b.csv", df!("y" => [2.0, 3.0]).unwrap(), true);
+        cdata.update(None);
+        assert!(cdata
+            .required_dataframes
+            .contains_key(&DataFrameQuery::whole(key(1))));
+
+        cdata.update_df_list = true;
+        cdata.update(None);
+        assert!(cdata
+            .required_dataframes
+            .contains_key(&DataFrameQuery::whole(key(0))));
+        assert!(!cdata
+            .required_dataframes
+            .contains_key(&DataFrameQuery::whole(key(1))));
+        assert_eq!(cdata.notifications.unread(), 0);
+    }
+
+    #[test]
+    fn dataframes_held_only_here_count_toward_the_budget() {
+        let fake = FakeBackend::default();
+        let mut cdata = with_fake(&fake);
+        cdata.df_cache_budget_mib = 7;
+        let big = DataFrame::new(vec![Series::new("x", vec![0.0f64; 400_000])]).unwrap();
+        let oldest = cdata.add_derived_df(key(0), "enu", big.clone());
+        let shown = cdata.add_derived_df(key(0), "enu", big.clone());
+        cdata.add_derived_df(key(0), "enu", big);
+
+        cdata.update(None);
+        cdata.request_df_transmission(shown);
+        cdata.update(None);
+
+        let (used, count) = cdata.df_cache_usage();
+        assert_eq!(count, 2);
+        assert!(used <= 7 * 1024 * 1024);
+        assert!(!cdata.is_local_df(oldest));
+        assert!(cdata.invalidated_df_ids.contains(&oldest));
+        assert!(cdata.is_local_df(shown));
+        assert_eq!(cdata.notifications.unread(), 1);
+    }
+
+    #[test]
+    fn failed_dataframe_is_not_requested_again_until_retry() {
+        let fake = FakeBackend::default();
+        let mut cdata = with_fake(&fake);
+        let query = DataFrameQuery::whole(key(7));
+
+        cdata.request_df_transmission(key(7));
+        cdata.update(None);
+        cdata.update(None);
+        assert!(cdata.df_errors.contains_key(&query));
+        assert_eq!(fake.state().requested_df_ids, vec![7]);
+        assert!(cdata.notifications.unread() > 0);
+
+        fake.add_dataframe(7, "/data/c.csv", df!("x" => [1.0]).unwrap(), false);
+        cdata.retry_df_transmission(&query);
+        cdata.update(None);
+        assert!(cdata.df_errors.is_empty());
+        assert!(cdata.required_dataframes[&query].is_some());
+    }
+
+    #[test]
+    fn cancelled_dataframe_waits_for_retry() {
+        let fake = FakeBackend::default();
+        fake.add_dataframe(2, "/data/d.csv", df!("x" => [1.0]).unwrap(), false);
+        let mut cdata = with_fake(&fake);
+        let query = DataFrameQuery::whole(key(2));
+        cdata.request_df_transmission(key(2));
+        // the fake answers immediately, so the transfer is cancelled before it is polled
+        cdata.df_transfers.insert(
+            query.clone(),
+            DataFrameTransfer {
+                promise: fake.get_df_request(
+                    h_analyzer_data::grpc_fs::DataFrameId { id: 2 },
+                    backend_talk::DataFrameProjection::default(),
+                    None,
+                    TransferProgress::default(),
+                ),
+                progress: TransferProgress::default(),
+            },
+        );
+
+        cdata.cancel_df_transmission(&query);
+        cdata.update(None);
+        assert!(cdata.df_transfers.is_empty());
+        assert!(cdata.required_dataframes[&query].is_none());
+        assert_eq!(fake.state().requested_df_ids, vec![2]);
+
+        cdata.retry_df_transmission(&query);
+        cdata.update(None);
+        assert!(cdata.required_dataframes[&query].is_some());
+        assert_eq!(fake.state().requested_df_ids, vec![2, 2]);
+    }
+
+    #[test]
+    fn visible_dataframes_are_fetched_first_in_parallel() {
+        let fake = FakeBackend::default();
+        for id in 1..4 {
+            fake.add_dataframe(id, "/data/e.csv", df!("x" => [1.0]).unwrap(), false);
+        }
+        let mut cdata = with_fake(&fake);
+        cdata.max_df_transfers = 2;
+        for id in 1..4 {
+            cdata.request_df_transmission(key(id));
+        }
+        // only the pane showing dataframe 3 is still drawn
+        cdata.frame_count = 10;
+        cdata.request_df_transmission(key(3));
+
+        cdata.update(None);
+        assert_eq!(fake.state().requested_df_ids, vec![3, 1]);
+        cdata.update(None);
+        assert_eq!(fake.state().requested_df_ids, vec![3, 1, 2]);
+        assert!(cdata.required_dataframes.values().all(|df| df.is_some()));
+    }
+
+    #[test]
+    fn projected_queries_are_cached_separately() {
+        let fake = FakeBackend::default();
+        let df = df!("x" => [1.0, 2.0, 3.0], "y" => [4.0, 5.0, 6.0]).unwrap();
+        fake.add_dataframe(0, "/data/f.csv", df, false);
+        let mut cdata = with_fake(&fake);
+        let page = DataFrameQuery::new(
+            key(0),
+            backend_talk::DataFrameProjection {
+                columns: Some(vec!["y".to_string()]),
+                rows: Some((1, 5)),
+            },
+        );
+
+        cdata.request_df_query(page.clone());
+        cdata.request_df_transmission(key(0));
+        cdata.update(None);
+
+        let sliced = cdata.required_df(&page).unwrap();
+        assert_eq!(sliced.get_column_names(), vec!["y"]);
+        assert_eq!(sliced.column("y").unwrap().f64().unwrap().get(0), Some(5.0));
+        assert_eq!(sliced.height(), 2);
+        let whole = cdata.required_df(&DataFrameQuery::whole(key(0))).unwrap();
+        assert_eq!(whole.shape(), (3, 2));
+    }
+
+    #[test]
+    fn parts_are_sliced_from_one_whole_dataframe_without_server_projections() {
+        let fake = FakeBackend::default();
+        fake.state().without_projections = true;
+        let df = df!("x" => [1.0, 2.0, 3.0], "y" => [4.0, 5.0, 6.0]).unwrap();
+        fake.add_dataframe(0, "/data/f.csv", df, false);
+        let mut cdata = with_fake(&fake);
+        let schema = DataFrameQuery::new(key(0), backend_talk::DataFrameProjection::schema());
+        let page = DataFrameQuery::new(
+            key(0),
+            backend_talk::DataFrameProjection {
+                columns: Some(vec!["y".to_string()]),
+                rows: Some((1, 5)),
+            },
+        );
+
+        cdata.request_df_query(schema.clone());
+        cdata.request_df_query(page.clone());
+        cdata.update(None);
+        cdata.update(None);
+
+        assert_eq!(cdata.required_df(&schema).unwrap().shape(), (0, 2));
+        let sliced = cdata.required_df(&page).unwrap();
+        assert_eq!(sliced.get_column_names(), vec!["y"]);
+        assert_eq!(sliced.height(), 2);
+        // a single transfer of the whole dataframe
+        assert_eq!(fake.state().requested_df_ids, vec![0]);
+    }
+
+    #[test]
+    fn dataframe_of_earlier_session_is_shown_from_cache_and_revalidated() {
+        let dir = std::env::temp_dir().join(format!("h_analyzer_session_{}", std::process::id()));
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0, 2.0]).unwrap(), false);
+        fake.state().fingerprints.insert(0, "v1".to_string());
+        let query = DataFrameQuery::whole(key(0));
+
+        let mut earlier = with_fake(&fake);
+        earlier.df_cache = df_cache::DataFrameCache::in_dir(dir.clone());
+        earlier.request_df_transmission(key(0));
+        earlier.update(None);
+        // entries are written by a background thread
+        let stored = (0..500).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            std::fs::read_dir(&dir).map_or(false, |entries| {
+                entries
+                    .flatten()
+                    .any(|entry| entry.path().extension().map_or(false, |ext| ext == "df"))
+            })
+        });
+        assert!(stored);
+
+        // same fingerprint, so the content on the server is not sent again
+        fake.state().dataframes.get_mut(&0).unwrap().1 = df!("x" => [9.0]).unwrap();
+        let mut later = with_fake(&fake);
+        later.df_cache = df_cache::DataFrameCache::in_dir(dir.clone());
+        later.request_df_transmission(key(0));
+        for _ in 0..500 {
+            later.update(None);
+            if later.required_dataframes[&query].is_some() && later.df_transfers.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let df = later.required_df(&query).unwrap();
+        assert_eq!(df.shape(), (2, 1));
+        assert_eq!(fake.state().requested_df_ids, vec![0, 0]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restored_dataframe_is_shown_from_cache_while_its_server_is_down() {
+        let dir = std::env::temp_dir().join(format!("h_analyzer_restore_{}", std::process::id()));
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0, 2.0]).unwrap(), false);
+        fake.state().fingerprints.insert(0, "v1".to_string());
+        let query = DataFrameQuery::whole(key(0));
+
+        let mut earlier = with_fake(&fake);
+        earlier.df_cache = df_cache::DataFrameCache::in_dir(dir.clone());
+        earlier.request_df_transmission(key(0));
+        earlier.update(None);
+        let stored = (0..500).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            std::fs::read_dir(&dir).map_or(false, |entries| {
+                entries
+                    .flatten()
+                    .any(|entry| entry.path().extension().map_or(false, |ext| ext == "df"))
+            })
+        });
+        assert!(stored);
+        let saved = earlier.session(None);
+
+        // no list of the server to find the file in, the saved session is enough
+        let later = FakeBackend::default();
+        later.state().unreachable = true;
+        let mut cdata = with_fake(&later);
+        cdata.df_cache = df_cache::DataFrameCache::in_dir(dir.clone());
+        cdata.restore_session(saved);
+        for _ in 0..500 {
+            cdata.request_df_query(query.clone());
+            cdata.update(None);
+            if cdata.required_dataframes[&query].is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(cdata.is_restoring(key(0)));
+        assert_eq!(cdata.required_df(&query).unwrap().shape(), (2, 1));
+        assert!(later.state().requested_df_ids.is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rosbag_still_loaded_is_not_loaded_again() {
+        let fake = FakeBackend::default();
+        let mut cdata = with_fake(&fake);
+        cdata.load_rosbag2("/data/run1".to_string());
+        // the server lists the world of the rosbag once it is loaded
+        fake.push_world_frame("run1", world_frame(0));
+        cdata.update(None);
+        cdata.update(None);
+        let saved = cdata.session(None);
+        assert_eq!(saved.rosbags[0].worlds, vec!["run1".to_string()]);
+
+        let mut cdata = with_fake(&fake);
+        cdata.restore_session(saved.clone());
+        cdata.update(None);
+        cdata.update(None);
+        assert_eq!(fake.state().loaded_rosbags.len(), 1);
+        assert_eq!(cdata.session(None).rosbags, saved.rosbags);
+
+        // a restarted server has lost it
+        let restarted = FakeBackend::default();
+        let mut cdata = with_fake(&restarted);
+        cdata.restore_session(saved);
+        cdata.update(None);
+        cdata.update(None);
+        assert_eq!(
+            restarted.state().loaded_rosbags,
+            vec!["/data/run1".to_string()]
+        );
+    }
+
+    #[test]
+    fn session_is_restored_under_new_ids() {
+        let earlier = FakeBackend::default();
+        earlier.add_dataframe(3, "/data/a.csv", df!("x" => [1.0]).unwrap(), false);
+        earlier.add_dataframe(5, "/data/b.csv", df!("y" => [2.0]).unwrap(), false);
+        earlier.push_world_frame("slam", world_frame(0));
+        let mut cdata = with_fake(&earlier);
+        cdata.load_rosbag2("/data/run1".to_string());
+        cdata.update(None);
+        let world = WorldKey {
+            backend: 0,
+            name: "slam".to_string(),
+        };
+        // a preview not yet confirmed is not saved
+        cdata.just_added_df_id_opt = Some(key(5));
+        assert_eq!(cdata.session(Some(&world)).dataframes.len(), 1);
+        cdata.just_added_df_id_opt = None;
+        let saved = cdata.session(Some(&world));
+        assert_eq!(saved.dataframes.len(), 2);
+
+        // the restarted server still has one of the files, under another id
+        let later = FakeBackend::default();
+        later.add_dataframe(0, "/data/b.csv", df!("y" => [2.0]).unwrap(), false);
+        let mut cdata = with_fake(&later);
+        assert_eq!(cdata.restore_session(saved), Some(world));
+        assert!(cdata.is_restoring(key(3)));
+        assert!(cdata.take_df_id_remap().is_empty());
+        cdata.update(None);
+        cdata.update(None);
+
+        let remap = cdata.take_df_id_remap();
+        assert_eq!(
+            remap,
+            [(key(3), key(1)), (key(5), key(0))].into_iter().collect()
+        );
+        assert!(!cdata.is_restoring(key(3)));
+        let later = later.state();
+        assert_eq!(later.loaded_rosbags, vec!["/data/run1".to_string()]);
+        let loaded: Vec<&str> = later
+            .loaded_files
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(loaded, vec!["/data/a.csv"]);
+    }
+
+    #[test]
+    fn rejected_credentials_do_not_count_as_disconnected() {
+        let fake = FakeBackend::default();
+        let mut cdata = with_fake(&fake);
+        cdata.health_check_interval = 0.0;
+
+        fake.state().rejected = true;
+        for _ in 0..DISCONNECTED_AFTER_FAILURES + 1 {
+            cdata.update(None);
+        }
+        assert_eq!(
+            cdata.connection_states(),
+            vec![("fake://", ConnectionState::Unauthorized)]
+        );
+        assert_eq!(cdata.notifications.unread(), 1);
+
+        fake.state().rejected = false;
+        cdata.update(None);
+        assert_eq!(cdata.backends[0].state, ConnectionState::Connected);
+    }
+
+    #[test]
+    fn restarted_backend_is_resynced_and_its_stale_ids_invalidated() {
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/a.csv", df!("x" => [1.0]).unwrap(), false);
+        fake.add_dataframe(1, "/data/b.csv", df!("y" => [2.0]).unwrap(), false);
+        let mut cdata = with_fake(&fake);
+        cdata.health_check_interval = 0.0;
+        cdata.request_df_transmission(key(0));
+        cdata.update(None);
+        cdata.update(None);
+        assert!(cdata.required_df(&DataFrameQuery::whole(key(0))).is_some());
+
+        fake.state().unreachable = true;
+        for _ in 0..DISCONNECTED_AFTER_FAILURES {
+            cdata.update(None);
+        }
+        assert_eq!(
+            cdata.connection_states(),
+            vec![("fake://", ConnectionState::Disconnected)]
+        );
+
+        // the server comes back with other files under the old ids
+        fake.state().dataframes.clear();
+        fake.add_dataframe(0, "/data/b.csv", df!("y" => [2.0]).unwrap(), false);
+        fake.state().unreachable = false;
+        cdata.update(None);
+
+        assert_eq!(cdata.backends[0].state, ConnectionState::Connected);
+        assert_eq!(cdata.latest_df_info_map.len(), 1);
+        assert_eq!(
+            cdata.invalidated_df_ids,
+            [key(0), key(1)].into_iter().collect()
+        );
+        assert!(cdata.required_df(&DataFrameQuery::whole(key(0))).is_none());
+    }
+
+    #[test]
+    fn pushed_down_query_is_listed_as_new_dataframe() {
+        let fake = FakeBackend::default();
+        let df = df!("x" => [1.0, 2.0, 3.0], "y" => [4.0, 5.0, 6.0]).unwrap();
+        fake.add_dataframe(0, "/data/g.csv", df, false);
+        fake.state().lazy_result = Some(df!("x" => [2.0, 3.0]).unwrap());
+        let mut cdata = with_fake(&fake);
+
+        // the schema has to arrive first
+        assert!(cdata.lazy_source(key(0)).unwrap().is_none());
+        cdata.update(None);
+        let plan = cdata
+            .lazy_source(key(0))
+            .unwrap()
+            .unwrap()
+            .filter(col("x").gt(lit(1.0)))
+            .select([col("x")]);
+        cdata.push_down_query(key(0), "x > 1".to_string(), plan);
+        cdata.update(None);
+
+        assert_eq!(fake.state().lazy_plans.len(), 1);
+        let (&id, _) = cdata
+            .latest_df_info_map
+            .iter()
+            .find(|(_, info)| info.df_path == "query: x > 1")
+            .unwrap();
+        assert!(cdata.is_local_df(id));
+
+        cdata.request_df_transmission(id);
+        cdata.update(None);
+        let result = cdata.required_df(&DataFrameQuery::whole(id)).unwrap();
+        assert_eq!(result.shape(), (2, 1));
+        // only the schema went over the wire
+        assert_eq!(fake.state().requested_df_ids, vec![0]);
+    }
+
+    #[test]
+    fn query_is_refused_where_the_schema_comes_only_with_everything() {
+        let fake = FakeBackend::default();
+        fake.add_dataframe(0, "/data/g.csv", df!("x" => [1.0, 2.0]).unwrap(), false);
+        fake.state().without_projections = true;
+        let mut cdata = with_fake(&fake);
+
+        assert!(cdata.lazy_source(key(0)).is_err());
+        cdata.update(None);
+        assert!(fake.state().requested_df_ids.is_empty());
+
+        // unless the whole dataframe is here for a pane already
+        cdata.request_df_transmission(key(0));
+        cdata.update(None);
+        let source = cdata.lazy_source(key(0)).unwrap().unwrap();
+        assert_eq!(source.collect().unwrap().shape(), (0, 1));
+    }
+
+    #[test]
+    fn dropped_file_is_previewed_without_backend() {
+        let fake = FakeBackend::default();
+        let mut cdata = with_fake(&fake);
+        let load_option = grpc_fs::DataFrameLoadOption {
+            has_header: true,
+            delimiter: ",".to_string(),
+            ..Default::default()
+        };
+
+        cdata.load_data_frame_from_bytes(&"trj.csv".to_string(), b"x,y\n1,2\n3,4\n", &load_option);
+        // the preview is requested like any other dataframe, but never leaves the frontend
+        assert!(cdata.get_just_loaded_data_frame().is_none());
+        cdata.update(None);
+        let preview = cdata.get_just_loaded_data_frame().unwrap();
+        assert_eq!(preview.shape(), (2, 2));
+        assert!(fake.state().requested_df_ids.is_empty());
+
+        // cancelling the dialog forgets the parsed file again
+        cdata.remove_preview_data_frame();
+        assert!(cdata.latest_df_info_map.is_empty());
+        assert!(fake.state().dataframes.is_empty());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn dropped_path_is_read_off_the_ui_thread() {
+        let path = std::env::temp_dir().join(format!("h_analyzer_drop_{}.csv", std::process::id()));
+        std::fs::write(&path, "x,y\n1,2\n3,4\n5,6\n").unwrap();
+        let fake = FakeBackend::default();
+        let mut cdata = with_fake(&fake);
+        let load_option = grpc_fs::DataFrameLoadOption {
+            has_header: true,
+            delimiter: ",".to_string(),
+            ..Default::default()
+        };
+
+        cdata.load_local_file(
+            &path.to_string_lossy().to_string(),
+            modal_window::LocalFile::Path(path.clone()),
+            &load_option,
+        );
+        let started = std::time::Instant::now();
+        let preview = loop {
+            cdata.update(None);
+            if let Some(preview) = cdata.get_just_loaded_data_frame() {
+                break preview;
+            }
+            assert!(started.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(preview.shape(), (3, 2));
+        assert!(fake.state().requested_df_ids.is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn world_frames_are_fetched_in_batches() {
+        let fake = FakeBackend::default();
+        for i in 0..5 {
+            fake.push_world_frame("slam", world_frame(i));
+        }
+        let mut cdata = with_fake(&fake);
+        cdata.world_batch_size = 2;
+
+        for _ in 0..5 {
+            cdata.update(None);
+        }
+
+        assert_eq!(cdata.world.history.len(), 5);
+        // one sample per request: 0..2, 2..4, 4..5
+        assert_eq!(cdata.sl_time_history.len(), 3);
+        let requested: Vec<u32> = fake
+            .state()
+            .requested_frames
+            .iter()
+            .map(|(_, idx)| *idx)
+            .collect();
+        assert_eq!(requested, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn world_frames_are_prefetched_within_window() {
+        let fake = FakeBackend::default();
+        for i in 0..10 {
+            fake.push_world_frame("slam", world_frame(i));
+        }
+        let mut cdata = with_fake(&fake);
+        cdata.world_playing = false;
+        cdata.world_prefetch_window = 2;
+
+        cdata.update(None);
+        cdata.update(None);
+        assert_eq!(cdata.world.history.len(), 3);
+
+        cdata.world.current_index = 2;
+        cdata.update(None);
+        assert_eq!(cdata.world.history.len(), 5);
+    }
+
+    #[test]
+    fn follow_latest_shows_newest_subscribed_frame() {
+        let fake = FakeBackend::default();
+        for i in 0..4 {
+            fake.push_world_frame("slam", world_frame(i));
+        }
+        let mut cdata = with_fake(&fake);
+        cdata.follow_latest = true;
+
+        cdata.update(None);
+
+        assert_eq!(cdata.world.history.len(), 4);
+        assert_eq!(cdata.world.current_index, 3);
+        assert!(fake.state().requested_frames.is_empty());
+    }
+
+    #[test]
+    fn world_history_is_reset_when_frames_loop() {
+        let fake = FakeBackend::default();
+        for i in 0..3 {
+            fake.push_world_frame("slam", world_frame(i));
+        }
+        let mut cdata = with_fake(&fake);
+
+        cdata.update(None);
+        assert_eq!(cdata.world.history.len(), 3);
+
+        // the backend started over, so the fourth frame has index 0 again
+        fake.push_world_frame("slam", world_frame(0));
+        cdata.update_world_list();
+        cdata.update(None);
+        assert_eq!(cdata.world.history.len(), 1);
+    }
+
+    #[test]
+    fn default_path_is_listed() {
+        let fake = FakeBackend::default();
+        fake.state().default_path = "/home/user".to_string();
+        fake.add_directory("/home/user", &["logs"], &["a.csv"]);
+        let mut cdata = with_fake(&fake);
+
+        cdata.update(None);
+
+        assert_eq!(cdata.current_path, "/home/user");
+        assert_eq!(cdata.default_path, "/home/user");
+        let fs_list = cdata.fs_list_promise.as_ref().unwrap().ready().unwrap();
+        assert_eq!(fs_list.as_ref().unwrap().files, vec!["a.csv".to_string()]);
+        assert_eq!(
+            fake.state().listed_paths,
+            vec!["/".to_string(), "/home/user".to_string()]
+        );
     }
 }
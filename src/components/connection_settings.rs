@@ -0,0 +1,237 @@
+use crate::backend_talk;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::local_backend;
+use eframe::egui;
+
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ConnectionProfile {
+    pub name: String,
+    pub server_address: String,
+    // connected next to the selected profile
+    #[serde(default)]
+    pub connected: bool,
+    #[serde(default)]
+    pub credentials: backend_talk::Credentials,
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ConnectionSettings {
+    profiles: Vec<ConnectionProfile>,
+    selected: usize,
+
+    #[serde(skip)]
+    new_profile: ConnectionProfile,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut profiles = vec![ConnectionProfile {
+            name: "default".to_string(),
+            server_address: backend_talk::DEFAULT_SERVER_ADDRESS.to_string(),
+            connected: false,
+            credentials: backend_talk::Credentials::None,
+        }];
+        // files of this machine, no server needed
+        #[cfg(not(target_arch = "wasm32"))]
+        profiles.push(ConnectionProfile {
+            name: "local files".to_string(),
+            server_address: local_backend::LOCAL_ADDRESS.to_string(),
+            connected: false,
+            credentials: backend_talk::Credentials::None,
+        });
+        Self {
+            profiles: profiles,
+            selected: 0,
+            new_profile: ConnectionProfile {
+                name: "".to_string(),
+                server_address: "http://".to_string(),
+                connected: false,
+                credentials: backend_talk::Credentials::None,
+            },
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub fn server_address(&self) -> String {
+        match self.profiles.get(self.selected) {
+            Some(profile) => profile.server_address.clone(),
+            None => backend_talk::DEFAULT_SERVER_ADDRESS.to_string(),
+        }
+    }
+
+    /// Servers to connect to. The selected profile comes first, it is the one the file
+    /// explorer browses.
+    pub fn server_addresses(&self) -> Vec<String> {
+        let mut addresses = vec![self.server_address()];
+        for (idx, profile) in self.profiles.iter().enumerate() {
+            if profile.connected && idx != self.selected {
+                addresses.push(profile.server_address.clone());
+            }
+        }
+        addresses
+    }
+
+    /// Like `server_addresses`, each with the credentials of its profile.
+    pub fn endpoints(&self) -> Vec<(String, backend_talk::Credentials)> {
+        let credentials = match self.profiles.get(self.selected) {
+            Some(profile) => profile.credentials.clone(),
+            None => backend_talk::Credentials::None,
+        };
+        let mut endpoints = vec![(self.server_address(), credentials)];
+        for (idx, profile) in self.profiles.iter().enumerate() {
+            if profile.connected && idx != self.selected {
+                endpoints.push((profile.server_address.clone(), profile.credentials.clone()));
+            }
+        }
+        endpoints
+    }
+
+    /// Select the profile pointing at `server_address`, adding one if no profile matches.
+    /// Used for the `?server=` query parameter and the `--server` command line flag.
+    pub fn apply_override(&mut self, server_address: String) {
+        if let Some(idx) = self
+            .profiles
+            .iter()
+            .position(|p| p.server_address == server_address)
+        {
+            self.selected = idx;
+            return;
+        }
+        self.profiles.push(ConnectionProfile {
+            name: "override".to_string(),
+            server_address: server_address,
+            connected: false,
+            credentials: backend_talk::Credentials::None,
+        });
+        self.selected = self.profiles.len() - 1;
+    }
+
+    /// Returns true if the connected profiles have been changed.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let mut del_idx = None;
+
+        egui::Grid::new("connection_profiles")
+            .num_columns(6)
+            .striped(true)
+            .show(ui, |ui| {
+                for (idx, profile) in self.profiles.iter_mut().enumerate() {
+                    ui.push_id(format!("profile_{}", idx), |ui| {
+                        if ui.radio(self.selected == idx, "").clicked() && self.selected != idx {
+                            self.selected = idx;
+                            changed = true;
+                        }
+                    });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut profile.name)
+                            .hint_text("profile name")
+                            .desired_width(100.0),
+                    );
+                    let resp = ui.add(
+                        egui::TextEdit::singleline(&mut profile.server_address)
+                            .hint_text("http://host:port"),
+                    );
+                    // reconnect once the address of a connected profile has been edited
+                    if resp.lost_focus() && (self.selected == idx || profile.connected) {
+                        changed = true;
+                    }
+                    ui.push_id(format!("credentials_{}", idx), |ui| {
+                        if credentials_ui(ui, &mut profile.credentials)
+                            && (self.selected == idx || profile.connected)
+                        {
+                            changed = true;
+                        }
+                    });
+                    ui.add_enabled_ui(self.selected != idx, |ui| {
+                        if ui.checkbox(&mut profile.connected, "connect").changed() {
+                            changed = true;
+                        }
+                    });
+                    ui.add_enabled_ui(self.selected != idx, |ui| {
+                        if ui.button("delete").clicked() {
+                            del_idx = Some(idx);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        if let Some(del_idx) = del_idx {
+            let removed = self.profiles.remove(del_idx);
+            changed |= removed.connected;
+            if self.selected > del_idx {
+                self.selected -= 1;
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_profile.name)
+                    .hint_text("profile name")
+                    .desired_width(100.0),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_profile.server_address)
+                    .hint_text("http://host:port"),
+            );
+            if ui.button("Add Profile").clicked() && !self.new_profile.name.is_empty() {
+                self.profiles.push(self.new_profile.clone());
+                self.new_profile = Self::default().new_profile;
+            }
+        });
+
+        changed
+    }
+}
+
+/// Kind and secret of `credentials`, returns true once they have been changed.
+fn credentials_ui(ui: &mut egui::Ui, credentials: &mut backend_talk::Credentials) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let kind = match credentials {
+            backend_talk::Credentials::None => "none",
+            backend_talk::Credentials::BearerToken(_) => "bearer token",
+            backend_talk::Credentials::ApiKey(_) => "api key",
+        };
+        egui::ComboBox::from_id_source("credentials_kind")
+            .selected_text(kind)
+            .width(100.0)
+            .show_ui(ui, |ui| {
+                let secret = match credentials {
+                    backend_talk::Credentials::None => String::new(),
+                    backend_talk::Credentials::BearerToken(secret)
+                    | backend_talk::Credentials::ApiKey(secret) => secret.clone(),
+                };
+                let choices = [
+                    backend_talk::Credentials::None,
+                    backend_talk::Credentials::BearerToken(secret.clone()),
+                    backend_talk::Credentials::ApiKey(secret),
+                ];
+                for (choice, label) in choices.into_iter().zip(["none", "bearer token", "api key"])
+                {
+                    let selected = label == kind;
+                    if ui.selectable_label(selected, label).clicked() && !selected {
+                        *credentials = choice;
+                        changed = true;
+                    }
+                }
+            });
+        if let backend_talk::Credentials::BearerToken(secret)
+        | backend_talk::Credentials::ApiKey(secret) = credentials
+        {
+            let resp = ui.add(
+                egui::TextEdit::singleline(secret)
+                    .password(true)
+                    .hint_text("secret")
+                    .desired_width(120.0),
+            );
+            // reconnect once the secret has been typed in, not on every key
+            changed |= resp.lost_focus();
+        }
+    });
+    changed
+}
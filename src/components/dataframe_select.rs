@@ -1,6 +1,8 @@
 use std::borrow::BorrowMut;
 
-use crate::common_data;
+use crate::backend_talk::DataFrameProjection;
+use crate::common_data::{self, DataFrameKey};
+use crate::request::TransferStats;
 use eframe::egui;
 use polars::prelude::*;
 
@@ -8,7 +10,7 @@ use super::modal_window::get_filename;
 
 #[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct DataFrameSelect {
-    pub dataframe_id: Option<usize>,
+    pub dataframe_id: Option<DataFrameKey>,
 }
 
 impl Default for DataFrameSelect {
@@ -18,39 +20,145 @@ impl Default for DataFrameSelect {
 }
 
 impl DataFrameSelect {
+    pub fn df_id(&self) -> DataFrameKey {
+        self.dataframe_id.clone().unwrap_or(DataFrameKey::new(0, 1))
+    }
+
+    /// Point the selection to the id its dataframe got when the session was restored.
+    pub fn remap_df_id(&mut self, remap: &std::collections::HashMap<DataFrameKey, DataFrameKey>) {
+        if let Some(new_id) = self.dataframe_id.and_then(|id| remap.get(&id)) {
+            self.dataframe_id = Some(*new_id);
+        }
+    }
+
+    /// Selection ui, returns `projection` of the selected dataframe once it has arrived.
     pub fn select_df<'a>(
         &mut self,
         idx: usize,
         ui: &mut egui::Ui,
         common_data: &'a mut common_data::CommonData,
+        projection: DataFrameProjection,
     ) -> Option<&'a mut DataFrame> {
-        let df_id = self.dataframe_id.clone().unwrap_or(1);
+        let df_id = self.df_id();
+        let mut picked = None;
         ui.push_id(format!("df_select_{}", idx), |ui| {
             ui.horizontal(|ui| -> Option<()> {
                 if common_data.latest_df_info_map.len() == 0 {
                     ui.label("Load DataFrame");
                 } else {
-                    let df_info = common_data.latest_df_info_map.get(&df_id).clone()?;
-                    let fname = get_filename(df_info.df_path.as_str());
+                    // a dataframe gone from the list can still be replaced by another one
+                    let fname = df_label(common_data, df_id).unwrap_or_default();
                     ui.label("Select DataFrame");
                     egui::ComboBox::from_label("")
                         .selected_text(format!("{}", fname))
                         .show_ui(ui, |ui| {
                             ui.style_mut().wrap = Some(false);
                             ui.set_min_width(60.0);
-                            for (id, df_info) in common_data.latest_df_info_map.iter() {
-                                let fname = get_filename(df_info.df_path.as_str());
-                                ui.selectable_value(&mut self.dataframe_id, Some(*id), fname);
+                            for id in common_data.latest_df_info_map.keys() {
+                                let fname = df_label(common_data, *id).unwrap_or_default();
+                                if ui
+                                    .selectable_value(&mut self.dataframe_id, Some(*id), fname)
+                                    .clicked()
+                                {
+                                    picked = Some(*id);
+                                }
                             }
                         });
                 }
                 None
             });
         });
+        // picking a dataframe again, even under the same id, acknowledges the restart
+        if let Some(picked) = picked {
+            common_data.invalidated_df_ids.remove(&picked);
+        } else if common_data.is_restoring(df_id) {
+            // the cached copy, if there is one, is shown meanwhile
+            ui.label("reloading the DataFrame of the last session");
+        } else if common_data.invalidated_df_ids.contains(&df_id) {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "this DataFrame is gone or its id now names another file, select it again",
+            );
+            return None;
+        }
 
         // request if the df is not available
-        common_data.request_df_transmission(df_id);
+        let query = common_data::DataFrameQuery::new(df_id, projection);
+        common_data.request_df_query(query.clone());
+        show_query_status(ui, common_data, &query);
+
+        common_data.required_df(&query)
+    }
+}
+
+/// File name of a listed dataframe, with its server once more than one is connected.
+pub fn df_label(common_data: &common_data::CommonData, df_id: DataFrameKey) -> Option<String> {
+    let df_info = common_data.latest_df_info_map.get(&df_id)?;
+    let fname = get_filename(df_info.df_path.as_str());
+    if common_data.backends.len() > 1 {
+        let address = common_data.server_address(df_id.backend)?;
+        Some(format!("{} ({})", fname, address))
+    } else {
+        Some(fname)
+    }
+}
 
-        Some(common_data.required_dataframes.get_mut(&df_id)?.as_mut()?)
+/// Progress of `query` while it is transferred, and why it failed if it did.
+fn show_query_status(
+    ui: &mut egui::Ui,
+    common_data: &mut common_data::CommonData,
+    query: &common_data::DataFrameQuery,
+) {
+    if let Some(stats) = common_data.df_transfer_progress(query) {
+        if transfer_progress_ui(ui, &stats) {
+            common_data.cancel_df_transmission(query);
+        }
     }
+
+    if let Some(err) = common_data.df_errors.get(query).cloned() {
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("failed to fetch DataFrame: {}", err),
+            );
+            if ui.button("retry").clicked() {
+                common_data.retry_df_transmission(query);
+            }
+        });
+    }
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MiB", bytes / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KiB", bytes / 1024.0)
+    }
+}
+
+/// Progress of a running transfer, returns true if its cancel button was clicked.
+pub fn transfer_progress_ui(ui: &mut egui::Ui, stats: &TransferStats) -> bool {
+    ui.horizontal(|ui| {
+        let received = format!("{} in {} chunks", format_bytes(stats.bytes), stats.chunks);
+        match stats.expected_bytes {
+            Some(expected) if expected > 0 => {
+                let fraction = stats.bytes as f32 / expected as f32;
+                ui.add(
+                    egui::ProgressBar::new(fraction.min(1.0))
+                        .desired_width(150.0)
+                        .text(format!("{} / {}", received, format_bytes(expected))),
+                );
+            }
+            _ => {
+                ui.spinner();
+                ui.label(received);
+            }
+        }
+        if stats.raw_bytes != stats.bytes {
+            ui.label(format!("compressed {:.1}x", stats.compression_ratio()));
+        }
+        ui.button("cancel").clicked()
+    })
+    .inner
 }
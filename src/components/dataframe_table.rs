@@ -1,6 +1,8 @@
 use core::panic;
 
-use crate::{common_data, components::dataframe_select};
+use crate::backend_talk::DataFrameProjection;
+use crate::common_data::{self, DataFrameKey, DataFrameQuery};
+use crate::components::dataframe_select;
 use eframe::egui;
 use egui_extras::{Column, TableBuilder};
 use polars::prelude::*;
@@ -14,7 +16,7 @@ impl ColumnSelectUi {
     fn new() -> Self {
         Self { column: None }
     }
-    fn show(&mut self, df: &mut DataFrame, ui: &mut egui::Ui, id_source: String) {
+    fn show(&mut self, df: &DataFrame, ui: &mut egui::Ui, id_source: String) {
         egui::ComboBox::from_id_source(id_source)
             .selected_text(self.column.clone().unwrap_or_default())
             .show_ui(ui, |ui| {
@@ -29,10 +31,21 @@ impl ColumnSelectUi {
     }
 }
 
+const HEIGHT_COLUMN: &str = " Ellipsoid Height (m)";
+
+/// Rows of the table pane fetched by a single request.
+const TABLE_PAGE_ROWS: usize = 200;
+
 #[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 struct ENUTransform {
     ui_lat: ColumnSelectUi,
     ui_lon: ColumnSelectUi,
+    // the table only holds the rows in view, so the transform is applied to every page shown
+    #[serde(default)]
+    applied: bool,
+    // other panes get the enu columns from a dataframe listed for them, made once the whole one is here
+    #[serde(skip)]
+    deriving: bool,
 }
 
 impl ENUTransform {
@@ -40,67 +53,93 @@ impl ENUTransform {
         Self {
             ui_lat: ColumnSelectUi::new(),
             ui_lon: ColumnSelectUi::new(),
+            applied: false,
+            deriving: false,
         }
     }
-    fn show(&mut self, df: &mut DataFrame, ui: &mut egui::Ui) {
+    fn show(&mut self, df: &DataFrame, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("ENU Transform: latitude: ");
             self.ui_lat.show(df, ui, format!("ENU_trfm_lat"));
             ui.label(", longitude: ");
             self.ui_lon.show(df, ui, format!("ENU_trfm_lon"));
-            if ui.button("execute").clicked() {
-                let df_clone = df.clone();
-                let lat_col = df_clone
-                    .column(self.ui_lat.column.as_ref().unwrap().as_str())
-                    .unwrap()
-                    .f64()
-                    .unwrap();
-                let lon_col = df_clone
-                    .column(self.ui_lon.column.as_ref().unwrap().as_str())
-                    .unwrap()
-                    .f64()
-                    .unwrap();
-                let hei_col = df_clone
-                    .column(" Ellipsoid Height (m)")
-                    .unwrap()
-                    .f64()
-                    .unwrap();
-
-                let res: Vec<(f64, f64, f64)> = lat_col
-                    .into_iter()
-                    .zip(lon_col.into_iter())
-                    .zip(hei_col.into_iter())
-                    .map(|(latlon, hei)| match (latlon, hei) {
-                        ((Some(lat), Some(lon)), Some(hei)) => {
-                            let (pe, pn, pu) = map_3d::geodetic2enu(
-                                lat.to_radians(),
-                                lon.to_radians(),
-                                hei,
-                                lat_col.get(0).unwrap().to_radians(),
-                                lon_col.get(0).unwrap().to_radians(),
-                                hei_col.get(0).unwrap(),
-                                map_3d::Ellipsoid::WGS84,
-                            );
-                            (pe, pn, pu)
-                        }
-                        _ => panic!("unexpected"),
-                    })
-                    .collect();
-                let mut e_vec = Vec::new();
-                let mut n_vec = Vec::new();
-                let mut u_vec = Vec::new();
-                for (e, n, u) in res.iter() {
-                    e_vec.push(*e);
-                    n_vec.push(*n);
-                    u_vec.push(*u);
+            if self.applied {
+                if ui.button("undo").clicked() {
+                    self.applied = false;
+                    self.deriving = false;
                 }
-
-                df.with_column(Series::new("ENU_E[m]", e_vec)).unwrap();
-                df.with_column(Series::new("ENU_N[m]", n_vec)).unwrap();
-                df.with_column(Series::new("ENU_U[m]", u_vec)).unwrap();
+            } else if ui.button("execute").clicked() {
+                self.applied = self.input_columns().is_some();
+                self.deriving = self.applied;
+            }
+            if self.deriving {
+                ui.spinner();
             }
         });
     }
+
+    fn input_columns(&self) -> Option<Vec<String>> {
+        Some(vec![
+            self.ui_lat.column.clone()?,
+            self.ui_lon.column.clone()?,
+            HEIGHT_COLUMN.to_string(),
+        ])
+    }
+
+    /// Latitude, longitude and height of the first row, the origin of the enu frame.
+    fn origin(first_row: &DataFrame) -> Option<(f64, f64, f64)> {
+        let value = |idx: usize| first_row.get_columns().get(idx)?.f64().ok()?.get(0);
+        Some((value(0)?, value(1)?, value(2)?))
+    }
+
+    fn transform(&self, df: &mut DataFrame, origin: (f64, f64, f64)) -> Option<()> {
+        let df_clone = df.clone();
+        let lat_col = df_clone
+            .column(self.ui_lat.column.as_ref()?.as_str())
+            .ok()?
+            .f64()
+            .ok()?;
+        let lon_col = df_clone
+            .column(self.ui_lon.column.as_ref()?.as_str())
+            .ok()?
+            .f64()
+            .ok()?;
+        let hei_col = df_clone.column(HEIGHT_COLUMN).ok()?.f64().ok()?;
+
+        let res: Vec<(f64, f64, f64)> = lat_col
+            .into_iter()
+            .zip(lon_col.into_iter())
+            .zip(hei_col.into_iter())
+            .map(|(latlon, hei)| match (latlon, hei) {
+                ((Some(lat), Some(lon)), Some(hei)) => {
+                    let (pe, pn, pu) = map_3d::geodetic2enu(
+                        lat.to_radians(),
+                        lon.to_radians(),
+                        hei,
+                        origin.0.to_radians(),
+                        origin.1.to_radians(),
+                        origin.2,
+                        map_3d::Ellipsoid::WGS84,
+                    );
+                    (pe, pn, pu)
+                }
+                _ => panic!("unexpected"),
+            })
+            .collect();
+        let mut e_vec = Vec::new();
+        let mut n_vec = Vec::new();
+        let mut u_vec = Vec::new();
+        for (e, n, u) in res.iter() {
+            e_vec.push(*e);
+            n_vec.push(*n);
+            u_vec.push(*u);
+        }
+
+        df.with_column(Series::new("ENU_E[m]", e_vec)).ok()?;
+        df.with_column(Series::new("ENU_N[m]", n_vec)).ok()?;
+        df.with_column(Series::new("ENU_U[m]", u_vec)).ok()?;
+        Some(())
+    }
 }
 
 pub fn show_dataframe_table(ui: &mut egui::Ui, df: &DataFrame) {
@@ -147,6 +186,97 @@ pub fn show_dataframe_table(ui: &mut egui::Ui, df: &DataFrame) {
     });
 }
 
+/// Table over a dataframe kept on the backend. Only the pages scrolled into view are fetched,
+/// `derive` adds computed columns to each of them.
+fn show_paged_dataframe_table(
+    ui: &mut egui::Ui,
+    common_data: &mut common_data::CommonData,
+    df_id: DataFrameKey,
+    schema: &DataFrame,
+    derive: impl Fn(&mut DataFrame),
+) {
+    let page_query = |page: usize| {
+        DataFrameQuery::new(
+            df_id,
+            DataFrameProjection {
+                columns: None,
+                rows: Some((page * TABLE_PAGE_ROWS, TABLE_PAGE_ROWS)),
+            },
+        )
+    };
+    // the number of rows is not known up front, so the table grows by a page until a short one arrives
+    let mut num_rows = 0;
+    for page in 0.. {
+        match common_data.required_dataframes.get(&page_query(page)) {
+            Some(Some(df)) if df.height() == TABLE_PAGE_ROWS => num_rows += TABLE_PAGE_ROWS,
+            Some(Some(df)) => {
+                num_rows += df.height();
+                break;
+            }
+            _ => {
+                num_rows += TABLE_PAGE_ROWS;
+                break;
+            }
+        }
+    }
+
+    egui::ScrollArea::both().show(ui, |ui| {
+        let column_names: Vec<String> = schema
+            .get_column_names()
+            .iter()
+            .map(|cname| cname.to_string())
+            .collect();
+
+        let text_height = egui::TextStyle::Body.resolve(ui.style()).size;
+        let table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .columns(Column::auto(), column_names.len() + 1);
+
+        let mut pages: std::collections::HashMap<usize, Option<DataFrame>> =
+            std::collections::HashMap::new();
+        table
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong("index");
+                });
+                for cname in column_names.iter() {
+                    header.col(|ui| {
+                        ui.strong(cname);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(text_height, num_rows, |row_index, mut row| {
+                    let page = row_index / TABLE_PAGE_ROWS;
+                    let page_df = pages.entry(page).or_insert_with(|| {
+                        let query = page_query(page);
+                        common_data.request_df_query(query.clone());
+                        let mut df = common_data.required_df(&query)?.clone();
+                        derive(&mut df);
+                        Some(df)
+                    });
+                    row.col(|ui| {
+                        ui.strong(row_index.to_string());
+                    });
+                    for cname in column_names.iter() {
+                        row.col(|ui| {
+                            let value = page_df
+                                .as_ref()
+                                .and_then(|df| df.column(cname).ok())
+                                .and_then(|col| col.get(row_index % TABLE_PAGE_ROWS).ok());
+                            match value {
+                                Some(value) => ui.label(value.to_string()),
+                                None => ui.weak("…"),
+                            };
+                        });
+                    }
+                });
+            });
+    });
+}
+
 #[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct DataFrameTablePane {
     dataframe_select: dataframe_select::DataFrameSelect,
@@ -163,6 +293,10 @@ impl Default for DataFrameTablePane {
 }
 
 impl DataFrameTablePane {
+    pub fn remap_df_ids(&mut self, remap: &std::collections::HashMap<DataFrameKey, DataFrameKey>) {
+        self.dataframe_select.remap_df_id(remap);
+    }
+
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
@@ -170,15 +304,58 @@ impl DataFrameTablePane {
     ) -> Option<()> {
         let common_data = &mut common_data_arc.lock().ok()?;
 
-        let mut df = self.dataframe_select.select_df(0, ui, common_data)?;
+        let mut schema = self
+            .dataframe_select
+            .select_df(0, ui, common_data, DataFrameProjection::schema())?
+            .clone();
+        let df_id = self.dataframe_select.df_id();
 
         egui::CollapsingHeader::new("Column-wise Operations")
             .default_open(true)
             .show(ui, |ui| {
-                self.df_manip_enu_transform.show(&mut df, ui);
+                self.df_manip_enu_transform.show(&schema, ui);
             });
 
-        show_dataframe_table(ui, df);
+        let enu = &mut self.df_manip_enu_transform;
+        let origin = match enu.input_columns() {
+            Some(columns) if enu.applied => {
+                let query = DataFrameQuery::new(
+                    df_id,
+                    DataFrameProjection {
+                        columns: Some(columns),
+                        rows: Some((0, 1)),
+                    },
+                );
+                common_data.request_df_query(query.clone());
+                common_data
+                    .required_dataframes
+                    .get(&query)
+                    .and_then(|first_row| first_row.as_ref())
+                    .and_then(ENUTransform::origin)
+            }
+            _ => None,
+        };
+        if let Some(origin) = origin {
+            enu.transform(&mut schema, origin);
+        }
+        if enu.deriving {
+            let whole = DataFrameQuery::whole(df_id);
+            common_data.request_df_query(whole.clone());
+            let whole_df = common_data.required_df(&whole).cloned();
+            if let (Some(origin), Some(mut df)) = (origin, whole_df) {
+                if enu.transform(&mut df, origin).is_some() {
+                    common_data.add_derived_df(df_id, "enu", df);
+                }
+                enu.deriving = false;
+            }
+        }
+        let enu = &self.df_manip_enu_transform;
+
+        show_paged_dataframe_table(ui, common_data, df_id, &schema, |page| {
+            if let Some(origin) = origin {
+                enu.transform(page, origin);
+            }
+        });
         None
     }
 }
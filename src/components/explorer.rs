@@ -1,5 +1,5 @@
-use crate::common_data::CommonData;
-use crate::components::modal_window;
+use crate::common_data::{CommonData, DataFrameKey, DataFrameQuery};
+use crate::components::{dataframe_select, modal_window};
 use eframe::egui;
 
 use super::modal_window::get_filename;
@@ -16,6 +16,10 @@ enum ExplorerTab {
 pub struct Explorer {
     explorer_tab: ExplorerTab,
     checked_map: std::collections::HashMap<String, bool>,
+    // sql typed per dataframe, run on the backend as a lazy query
+    query_texts: std::collections::HashMap<DataFrameKey, String>,
+    // waits here until the schema of its dataframe has arrived
+    pending_query: Option<(DataFrameKey, String)>,
 }
 
 impl Default for Explorer {
@@ -23,6 +27,8 @@ impl Default for Explorer {
         Self {
             explorer_tab: ExplorerTab::FILESYSTEM,
             checked_map: std::collections::HashMap::new(),
+            query_texts: std::collections::HashMap::new(),
+            pending_query: None,
         }
     }
 }
@@ -52,7 +58,7 @@ impl Explorer {
                 if ui.button("refresh").clicked() {
                     common_data.fs_list_promise = Some(
                         common_data
-                            .backend
+                            .primary_backend()
                             .request_list(common_data.current_path.clone()),
                     );
                 }
@@ -120,14 +126,17 @@ impl Explorer {
                                 .join(filename);
                             let fullpath = nfp.to_string_lossy().to_string();
                             common_data.modal_window_input_opt =
-                                Some(modal_window::ModalWindowInput { filepath: fullpath });
+                                Some(modal_window::ModalWindowInput {
+                                    filepath: fullpath,
+                                    local_file: None,
+                                });
                         }
                     }
 
                     if update_list {
                         common_data.fs_list_promise = Some(
                             common_data
-                                .backend
+                                .primary_backend()
                                 .request_list(common_data.current_path.to_string()),
                         );
                     }
@@ -141,45 +150,153 @@ impl Explorer {
                 }
             }
             ExplorerTab::DATAFRAME => {
+                // servers without a change stream only list changes made by others when asked
+                if ui.button("refresh").clicked() {
+                    common_data.update_df_list = true;
+                }
+                ui.add(
+                    egui::DragValue::new(&mut common_data.max_df_transfers)
+                        .clamp_range(1..=16)
+                        .prefix("parallel transfers: "),
+                );
+                ui.horizontal(|ui| {
+                    let (used, count) = common_data.df_cache_usage();
+                    ui.label(format!(
+                        "cache: {} in {} DataFrames, budget",
+                        dataframe_select::format_bytes(used),
+                        count
+                    ))
+                    .on_hover_text(
+                        "including files parsed here and query results, which are gone once dropped",
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut common_data.df_cache_budget_mib)
+                            .clamp_range(16..=16384)
+                            .suffix(" MiB"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("kept across sessions, budget");
+                    let budget = ui.add(
+                        egui::DragValue::new(&mut common_data.disk_cache_budget_mib)
+                            .clamp_range(16..=65536)
+                            .suffix(" MiB"),
+                    );
+                    if budget.changed() {
+                        common_data.apply_disk_cache_budget();
+                    }
+                });
+                ui.separator();
+                let mut cancelled = None;
+                let query_texts = &mut self.query_texts;
+                let pending_query = &mut self.pending_query;
                 egui::ScrollArea::both().show(ui, |ui| {
                     for (id, df_info) in common_data.latest_df_info_map.iter() {
-                        ui.push_id(
-                            format!("df_list_{}", df_info.clone().id.unwrap().id),
-                            |ui| {
-                                ui.collapsing(get_filename(&df_info.df_path), |ui| {
-                                    egui::Grid::new("colors")
-                                        .num_columns(2)
-                                        .spacing([12.0, 8.0])
-                                        .striped(true)
-                                        .show(ui, |ui| {
-                                            ui.label("ID");
-                                            ui.label(format!("{}", id));
-                                            ui.end_row();
+                        let label = dataframe_select::df_label(&common_data, *id)
+                            .unwrap_or_else(|| get_filename(&df_info.df_path));
+                        ui.push_id(format!("df_list_{}_{}", id.backend, id.id), |ui| {
+                            ui.collapsing(label, |ui| {
+                                egui::Grid::new("colors")
+                                    .num_columns(2)
+                                    .spacing([12.0, 8.0])
+                                    .striped(true)
+                                    .show(ui, |ui| {
+                                        ui.label("ID");
+                                        ui.label(format!("{}", id.id));
+                                        ui.end_row();
+
+                                        ui.label("Server");
+                                        ui.label(
+                                            common_data
+                                                .server_address(id.backend)
+                                                .unwrap_or_default(),
+                                        );
+                                        ui.end_row();
+
+                                        ui.label("FilePath");
+                                        ui.label(format!("{}", df_info.df_path));
+                                        ui.end_row();
 
-                                            ui.label("FilePath");
-                                            ui.label(format!("{}", df_info.df_path));
+                                        if let Some(df_opt) = common_data
+                                            .required_dataframes
+                                            .get(&DataFrameQuery::whole(*id))
+                                        {
+                                            ui.label("Availability");
+                                            ui.label(format!("{}", df_opt.is_some()));
                                             ui.end_row();
 
-                                            if let Some(df_opt) = common_data
-                                                .required_dataframes
-                                                .get(&(df_info.clone().id.unwrap().id as usize))
-                                            {
-                                                ui.label("Availability");
-                                                ui.label(format!("{}", df_opt.is_some()));
+                                            if let Some(df) = df_opt {
+                                                ui.label("Shape");
+                                                ui.label(format!("{:?}", df.shape()));
                                                 ui.end_row();
+                                            }
+                                        }
+
+                                        if let Some(stats) = common_data.df_transfer_stats.get(id) {
+                                            ui.label("Received");
+                                            ui.label(format!(
+                                                "{} bytes, compression {:.1}x",
+                                                stats.bytes,
+                                                stats.compression_ratio()
+                                            ));
+                                            ui.end_row();
+                                        }
 
-                                                if let Some(df) = df_opt {
-                                                    ui.label("Shape");
-                                                    ui.label(format!("{:?}", df.shape()));
-                                                    ui.end_row();
-                                                }
+                                        // whole dataframe as well as parts fetched by panes
+                                        for (query, stats) in common_data.df_transfers_of(*id) {
+                                            ui.label("Transfer");
+                                            if dataframe_select::transfer_progress_ui(ui, &stats) {
+                                                cancelled = Some(query);
                                             }
-                                        });
+                                            ui.end_row();
+                                        }
+                                    });
+
+                                ui.horizontal(|ui| {
+                                    let sql = query_texts.entry(*id).or_default();
+                                    ui.add(
+                                        egui::TextEdit::singleline(sql)
+                                            .hint_text("SELECT * FROM df WHERE ..."),
+                                    );
+                                    let waiting = pending_query
+                                        .as_ref()
+                                        .map_or(false, |(df_id, _)| df_id == id);
+                                    if waiting {
+                                        ui.spinner();
+                                    } else if ui.button("run").clicked() && !sql.is_empty() {
+                                        *pending_query = Some((*id, sql.clone()));
+                                    }
                                 });
-                            },
-                        );
+                            });
+                        });
                     }
                 });
+                if let Some(query) = cancelled {
+                    common_data.cancel_df_transmission(&query);
+                }
+                if let Some((df_id, sql)) = self.pending_query.clone() {
+                    match common_data.lazy_source(df_id) {
+                        Ok(Some(source)) => {
+                            self.pending_query = None;
+                            let mut context = polars::sql::SQLContext::new();
+                            context.register("df", source);
+                            match context.execute(&sql) {
+                                Ok(plan) => common_data.push_down_query(df_id, sql, plan),
+                                Err(e) => common_data.notifications.push(
+                                    "run_lazy_query",
+                                    &tonic::Status::invalid_argument(e.to_string()),
+                                ),
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            self.pending_query = None;
+                            common_data
+                                .notifications
+                                .push("run_lazy_query", &tonic::Status::failed_precondition(e));
+                        }
+                    }
+                }
             }
             ExplorerTab::ROSBAG => {}
         }
@@ -1,5 +1,8 @@
+pub mod connection_settings;
 pub mod dataframe_select;
 pub mod dataframe_table;
 pub mod explorer;
 pub mod modal_window;
+pub mod network_inspector;
+pub mod notifications;
 pub mod plotter_2d;
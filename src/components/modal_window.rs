@@ -2,9 +2,41 @@ use super::dataframe_table;
 use eframe::egui::{self};
 use polars::prelude::*;
 
+/// A file of the user's machine, parsed in the frontend instead of by the backend.
+#[derive(Clone)]
+pub enum LocalFile {
+    /// handed over by the browser
+    Contents(std::sync::Arc<[u8]>),
+    /// dropped onto the native window, read once it is loaded
+    #[cfg(not(target_arch = "wasm32"))]
+    Path(std::path::PathBuf),
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct ModalWindowInput {
     pub filepath: String,
+    // none for files on the backend
+    #[serde(skip)]
+    pub local_file: Option<LocalFile>,
+}
+
+impl ModalWindowInput {
+    /// A file dropped onto the window. Browsers hand over its contents, native builds its path.
+    pub fn from_dropped(file: &egui::DroppedFile) -> Option<Self> {
+        let (filepath, local_file) = match (&file.bytes, &file.path) {
+            (Some(bytes), _) => (file.name.clone(), LocalFile::Contents(bytes.clone())),
+            #[cfg(not(target_arch = "wasm32"))]
+            (None, Some(path)) => (
+                path.to_string_lossy().to_string(),
+                LocalFile::Path(path.clone()),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            filepath: filepath,
+            local_file: Some(local_file),
+        })
+    }
 }
 
 pub fn get_filename(fullpath: &str) -> String {
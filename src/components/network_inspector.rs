@@ -0,0 +1,193 @@
+use crate::common_data;
+use crate::rpc_log::{RpcOutcome, RpcRecord};
+use eframe::egui;
+use egui_extras::{Column, TableBuilder};
+
+/// Width of a bar of the latency histogram.
+const HISTOGRAM_BUCKET_MS: f64 = 10.0;
+
+/// Pane listing the rpcs sent to the backends, with a latency histogram per method.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct NetworkInspector {
+    /// shown calls contain this in their method, arguments or server address
+    filter: String,
+    failed_only: bool,
+    histogram_method: Option<String>,
+}
+
+impl Default for NetworkInspector {
+    fn default() -> Self {
+        Self {
+            filter: String::new(),
+            failed_only: false,
+            histogram_method: None,
+        }
+    }
+}
+
+impl NetworkInspector {
+    fn matches(&self, record: &RpcRecord) -> bool {
+        if self.failed_only && !matches!(record.outcome, RpcOutcome::Failed(..)) {
+            return false;
+        }
+        let filter = self.filter.to_lowercase();
+        filter.is_empty()
+            || record.method.contains(filter.as_str())
+            || record.args.to_lowercase().contains(filter.as_str())
+            || record
+                .server_address
+                .to_lowercase()
+                .contains(filter.as_str())
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        common_data_arc: std::sync::Arc<std::sync::Mutex<common_data::CommonData>>,
+    ) -> Option<()> {
+        // copied out, so that the backends are not blocked while the table is drawn
+        let (rpc_log, records) = {
+            let common_data = common_data_arc.lock().ok()?;
+            (common_data.rpc_log.clone(), common_data.rpc_log.records())
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("filter");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("method, arguments or server")
+                    .desired_width(200.0),
+            );
+            ui.checkbox(&mut self.failed_only, "failed only");
+            if ui.button("clear").clicked() {
+                rpc_log.clear();
+            }
+        });
+        let shown: Vec<&RpcRecord> = records.iter().filter(|r| self.matches(r)).collect();
+
+        let mut methods: Vec<&str> = shown.iter().map(|r| r.method).collect();
+        methods.sort();
+        methods.dedup();
+        ui.horizontal(|ui| {
+            ui.label("latency of");
+            egui::ComboBox::from_id_source("histogram_method")
+                .selected_text(self.histogram_method.clone().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for method in methods {
+                        ui.selectable_value(
+                            &mut self.histogram_method,
+                            Some(method.to_string()),
+                            method,
+                        );
+                    }
+                });
+        });
+        if let Some(method) = &self.histogram_method {
+            latency_histogram(ui, method, &shown);
+        }
+
+        ui.separator();
+        ui.label(format!("{} of {} calls", shown.len(), records.len()));
+        TableBuilder::new(ui)
+            .striped(true)
+            .stick_to_bottom(true)
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto().clip(true))
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for title in [
+                    "started",
+                    "server",
+                    "method",
+                    "arguments",
+                    "latency",
+                    "bytes",
+                    "outcome",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, shown.len(), |row_index, mut row| {
+                    let record = shown[row_index];
+                    row.col(|ui| {
+                        ui.label(time_text(record.started_at));
+                    });
+                    row.col(|ui| {
+                        ui.label(record.server_address.as_str());
+                    });
+                    row.col(|ui| {
+                        ui.label(record.method);
+                    });
+                    row.col(|ui| {
+                        ui.label(record.args.as_str());
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.1} ms", record.elapsed().as_secs_f64() * 1e3));
+                    });
+                    row.col(|ui| {
+                        ui.label(record.bytes.map_or("-".to_string(), |b| b.to_string()));
+                    });
+                    row.col(|ui| match &record.outcome {
+                        RpcOutcome::Pending => {
+                            ui.spinner();
+                        }
+                        RpcOutcome::Ok => {
+                            ui.label("ok");
+                        }
+                        RpcOutcome::Failed(code, message) => {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{:?}", code))
+                                .on_hover_text(message.as_str());
+                        }
+                    });
+                });
+            });
+        Some(())
+    }
+}
+
+fn time_text(timestamp: web_time::SystemTime) -> String {
+    let millis = timestamp
+        .duration_since(web_time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+        % 86_400_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+/// Number of finished calls of `method` per latency bucket.
+fn latency_histogram(ui: &mut egui::Ui, method: &str, records: &[&RpcRecord]) {
+    let mut counts = std::collections::BTreeMap::new();
+    for record in records.iter().filter(|r| r.method == method) {
+        if let Some(latency) = record.latency {
+            let bucket = (latency.as_secs_f64() * 1e3 / HISTOGRAM_BUCKET_MS) as usize;
+            *counts.entry(bucket).or_insert(0) += 1;
+        }
+    }
+    let bars = counts
+        .into_iter()
+        .map(|(bucket, count)| {
+            egui_plot::Bar::new((bucket as f64 + 0.5) * HISTOGRAM_BUCKET_MS, count as f64)
+                .width(HISTOGRAM_BUCKET_MS)
+        })
+        .collect();
+    egui_plot::Plot::new("rpc_latency_histogram")
+        .height(150.0)
+        .x_axis_label("latency [ms]")
+        .y_axis_label("calls")
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(egui_plot::BarChart::new(bars).name(method));
+        });
+}
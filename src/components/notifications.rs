@@ -0,0 +1,143 @@
+use eframe::egui;
+
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_HISTORY: usize = 200;
+
+#[derive(Clone)]
+pub struct Notification {
+    pub endpoint: String,
+    pub code: tonic::Code,
+    pub message: String,
+    pub timestamp: web_time::SystemTime,
+    pub count: usize,
+
+    shown_at: web_time::Instant,
+}
+
+impl Notification {
+    fn time_text(&self) -> String {
+        let secs = self
+            .timestamp
+            .duration_since(web_time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            % 86400;
+        format!(
+            "{:02}:{:02}:{:02} UTC",
+            secs / 3600,
+            (secs / 60) % 60,
+            secs % 60
+        )
+    }
+
+    fn summary(&self) -> String {
+        format!("{} failed: {:?}", self.endpoint, self.code)
+    }
+}
+
+/// Collects failed rpcs. New failures pop up as toasts, all of them stay browsable in the history window.
+#[derive(Default)]
+pub struct NotificationCenter {
+    history: std::collections::VecDeque<Notification>,
+    unread: usize,
+    // message of the last rejection for missing or wrong credentials, until the app prompts for them
+    credentials_rejected: Option<String>,
+}
+
+impl NotificationCenter {
+    pub fn push(&mut self, endpoint: &str, status: &tonic::Status) {
+        log::warn!("{} failed: {}", endpoint, status);
+        self.unread += 1;
+        if matches!(
+            status.code(),
+            tonic::Code::Unauthenticated | tonic::Code::PermissionDenied
+        ) {
+            self.credentials_rejected = Some(status.message().to_string());
+        }
+        // a request retried every frame would flood the history, so repeats are folded into the last entry
+        if let Some(last) = self.history.back_mut() {
+            if last.endpoint == endpoint
+                && last.code == status.code()
+                && last.message == status.message()
+            {
+                last.count += 1;
+                last.timestamp = web_time::SystemTime::now();
+                return;
+            }
+        }
+        self.history.push_back(Notification {
+            endpoint: endpoint.to_string(),
+            code: status.code(),
+            message: status.message().to_string(),
+            timestamp: web_time::SystemTime::now(),
+            count: 1,
+            shown_at: web_time::Instant::now(),
+        });
+        if self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    /// Why a server turned a request down for its credentials, reported once per rejection.
+    pub fn take_credentials_rejected(&mut self) -> Option<String> {
+        self.credentials_rejected.take()
+    }
+
+    pub fn show_toasts(&self, ctx: &egui::Context) {
+        let toasts: Vec<&Notification> = self
+            .history
+            .iter()
+            .filter(|n| n.shown_at.elapsed() < TOAST_DURATION)
+            .collect();
+        if toasts.is_empty() {
+            return;
+        }
+        egui::Area::new("notification_toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-10.0, -40.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                for toast in toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(ui.visuals().error_fg_color, toast.summary());
+                        ui.label(toast.message.as_str());
+                    });
+                }
+            });
+    }
+
+    pub fn show_history(&mut self, ui: &mut egui::Ui) {
+        self.unread = 0;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} notifications", self.history.len()));
+            if ui.button("clear").clicked() {
+                self.history.clear();
+            }
+        });
+        ui.separator();
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("notification_history")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("time");
+                    ui.strong("endpoint");
+                    ui.strong("code");
+                    ui.strong("count");
+                    ui.strong("message");
+                    ui.end_row();
+                    for n in self.history.iter().rev() {
+                        ui.label(n.time_text());
+                        ui.label(n.endpoint.as_str());
+                        ui.label(format!("{:?}", n.code));
+                        ui.label(format!("{}", n.count));
+                        ui.label(n.message.as_str());
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
@@ -1,6 +1,7 @@
 use std::ops::Deref;
 
-use crate::common_data::{self};
+use crate::backend_talk::DataFrameProjection;
+use crate::common_data::{self, DataFrameQuery};
 use crate::components::dataframe_select;
 
 use crate::unwrap_or_continue;
@@ -96,7 +97,32 @@ impl Default for Plotter2D {
     }
 }
 
+impl SeriesInfo {
+    /// Columns the series is drawn from, once they are chosen.
+    fn projection(&self) -> Option<DataFrameProjection> {
+        let mut columns = vec![self.x_column.clone()?, self.y_column.clone()?];
+        if self.plot_type == PlotType::Pose {
+            columns.push(self.theta_column.clone()?);
+        }
+        columns.sort();
+        columns.dedup();
+        Some(DataFrameProjection {
+            columns: Some(columns),
+            rows: None,
+        })
+    }
+}
+
 impl Plotter2D {
+    pub fn remap_df_ids(
+        &mut self,
+        remap: &std::collections::HashMap<common_data::DataFrameKey, common_data::DataFrameKey>,
+    ) {
+        for selector in self.series_df_selectors.iter_mut() {
+            selector.remap_df_id(remap);
+        }
+    }
+
     fn entity_settings(
         idx: usize,
         info: &mut SeriesInfo,
@@ -262,9 +288,13 @@ impl Plotter2D {
                         let selector = unwrap_or_continue!(selector_iter.next());
                         ui.horizontal(|ui| {
                             series_df = match info.source {
-                                SeriesSource::DataFrame => {
-                                    selector.select_df(idx + 1, ui, common_data)
-                                }
+                                // the column names are enough to set up the series
+                                SeriesSource::DataFrame => selector.select_df(
+                                    idx + 1,
+                                    ui,
+                                    common_data,
+                                    DataFrameProjection::schema(),
+                                ),
                                 SeriesSource::WorldFrame => None,
                             };
 
@@ -476,11 +506,15 @@ impl Plotter2D {
                     if !s_info.visible {
                         continue;
                     }
+                    // only the plotted columns are fetched
+                    let query =
+                        DataFrameQuery::new(df_id, unwrap_or_continue!(s_info.projection()));
+                    common_data.request_df_query(query.clone());
                     let local_df = match s_info.source {
                         SeriesSource::DataFrame => Some(
                             common_data
                                 .required_dataframes
-                                .get(&df_id)
+                                .get(&query)
                                 .unwrap_or(&None)
                                 .clone()
                                 .unwrap_or_default(),
@@ -0,0 +1,40 @@
+//! Csv parsing in the frontend, for the local backend and for files handed over by the browser.
+
+use h_analyzer_data::grpc_fs;
+use polars::prelude::*;
+
+/// Reads csv `contents` the same way the analyzer server reads files for `load_option`.
+pub fn read_csv(
+    contents: &[u8],
+    load_option: &grpc_fs::DataFrameLoadOption,
+) -> PolarsResult<DataFrame> {
+    let separator = load_option.delimiter.bytes().next().unwrap_or(b',');
+    CsvReader::new(std::io::Cursor::new(contents))
+        .has_header(load_option.has_header)
+        .with_skip_rows(load_option.skip_row_num_before_header as usize)
+        .with_skip_rows_after_header(load_option.skip_row_num_after_header as usize)
+        .with_separator(separator)
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_is_read_with_load_option() {
+        let contents = b"# recorded on robot\nx;y\nunit;unit\n1.0;2.0\n3.0;4.0\n";
+        let load_option = grpc_fs::DataFrameLoadOption {
+            source_type: grpc_fs::DataFrameSourceType::Csv.into(),
+            has_header: true,
+            skip_row_num_before_header: 1,
+            skip_row_num_after_header: 1,
+            delimiter: ";".to_string(),
+            updated: false,
+        };
+
+        let df = read_csv(contents, &load_option).unwrap();
+        assert_eq!(df.get_column_names(), vec!["x", "y"]);
+        assert_eq!(df.column("y").unwrap().f64().unwrap().get(1), Some(4.0));
+    }
+}
@@ -0,0 +1,427 @@
+//! Fetched dataframes kept across sessions, in IndexedDB on the web and in a cache directory natively.
+//!
+//! Entries are keyed by backend, file, load option and the part of the dataframe fetched. They carry
+//! the fingerprint the backend sent along with the content, which is handed back to it to find out
+//! whether the cached copy is still current. Once the entries outgrow the budget, the least recently
+//! used ones are removed.
+
+use crate::backend_talk::{grpc_fs, DataFrameProjection};
+use crate::unwrap_or_continue;
+use polars::prelude::*;
+use poll_promise::Promise;
+
+pub const DEFAULT_BUDGET: u64 = 512 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CacheKey {
+    pub server_address: String,
+    pub df_path: String,
+    /// without the `updated` flag, which changes whenever the server reloads the file
+    pub load_option: Option<grpc_fs::DataFrameLoadOption>,
+    pub projection: DataFrameProjection,
+}
+
+impl CacheKey {
+    pub fn new(
+        server_address: &str,
+        df_info: &grpc_fs::DataFrameInfo,
+        projection: &DataFrameProjection,
+    ) -> Self {
+        Self {
+            server_address: server_address.to_string(),
+            df_path: df_info.df_path.clone(),
+            load_option: df_info.load_option.as_ref().map(|load_option| {
+                grpc_fs::DataFrameLoadOption {
+                    updated: false,
+                    ..load_option.clone()
+                }
+            }),
+            projection: projection.clone(),
+        }
+    }
+
+    /// Same on every build and platform, unlike the hashers of std, so entries outlive updates.
+    fn file_name(&self) -> String {
+        // 64 bit FNV-1a over the serialized key
+        let bytes = bincode::serialize(self).unwrap_or_default();
+        let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}.df", hash)
+    }
+}
+
+pub struct CachedDataFrame {
+    pub fingerprint: String,
+    pub df: DataFrame,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct StoredEntry {
+    // file names are hashes, so the key is stored to rule out collisions
+    key: CacheKey,
+    fingerprint: String,
+    ipc: Vec<u8>,
+}
+
+fn encode(key: CacheKey, entry: &CachedDataFrame) -> Option<Vec<u8>> {
+    let mut ipc = Vec::new();
+    IpcStreamWriter::new(&mut ipc)
+        .finish(&mut entry.df.clone())
+        .ok()?;
+    bincode::serialize(&StoredEntry {
+        key: key,
+        fingerprint: entry.fingerprint.clone(),
+        ipc: ipc,
+    })
+    .ok()
+}
+
+fn decode(key: &CacheKey, bytes: &[u8]) -> Option<CachedDataFrame> {
+    let stored: StoredEntry = bincode::deserialize(bytes).ok()?;
+    if &stored.key != key {
+        return None;
+    }
+    let df = IpcStreamReader::new(std::io::Cursor::new(stored.ipc))
+        .finish()
+        .ok()?;
+    Some(CachedDataFrame {
+        fingerprint: stored.fingerprint,
+        df: df,
+    })
+}
+
+/// Names of the entries to remove so that the rest fits into `budget`, least recently used first.
+/// `entries` are (name, size, last use), the entry named `keep` was just stored and stays.
+fn entries_over_budget(
+    mut entries: Vec<(String, u64, u64)>,
+    budget: u64,
+    keep: &str,
+) -> Vec<String> {
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort_by_key(|(_, _, last_used)| *last_used);
+    let mut removed = Vec::new();
+    for (name, size, _) in entries {
+        if total <= budget {
+            break;
+        }
+        if name == keep {
+            continue;
+        }
+        total -= size;
+        removed.push(name);
+    }
+    removed
+}
+
+/// Where the entries are stored. The default one keeps nothing, tests use it.
+#[derive(Clone, Default)]
+pub struct DataFrameCache {
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: Option<std::path::PathBuf>,
+    #[cfg(target_arch = "wasm32")]
+    enabled: bool,
+    budget: u64,
+}
+
+impl DataFrameCache {
+    pub fn with_budget(mut self, budget: u64) -> Self {
+        self.budget = budget;
+        self
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn millis_since_epoch(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Removes the least recently used entries in `dir` beyond `budget`. An entry was last used when
+/// it was written or, if later, when its `.used` marker was touched by a load.
+#[cfg(not(target_arch = "wasm32"))]
+fn prune_dir(dir: &std::path::Path, budget: u64, keep: &str) -> std::io::Result<()> {
+    let mut entries = Vec::new();
+    for dir_entry in std::fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        if path.extension().map_or(true, |extension| extension != "df") {
+            continue;
+        }
+        let metadata = unwrap_or_continue!(std::fs::metadata(&path).ok());
+        let written = metadata.modified()?;
+        let used = std::fs::metadata(path.with_extension("used"))
+            .and_then(|used| used.modified())
+            .map_or(written, |used| used.max(written));
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        entries.push((name, metadata.len(), millis_since_epoch(used)));
+    }
+    for name in entries_over_budget(entries, budget, keep) {
+        let path = dir.join(name);
+        std::fs::remove_file(&path)?;
+        let _ = std::fs::remove_file(path.with_extension("used"));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DataFrameCache {
+    /// Entries below the user's cache directory.
+    pub fn persistent() -> Self {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| std::env::var_os("LOCALAPPDATA").map(std::path::PathBuf::from))
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".cache"))
+            })
+            .unwrap_or_else(std::env::temp_dir);
+        Self::in_dir(base.join("h_analyzer").join("dataframes"))
+    }
+
+    pub fn in_dir(dir: std::path::PathBuf) -> Self {
+        Self {
+            dir: Some(dir),
+            budget: DEFAULT_BUDGET,
+        }
+    }
+
+    pub fn load(&self, key: &CacheKey) -> Promise<Option<CachedDataFrame>> {
+        let path = match &self.dir {
+            Some(dir) => dir.join(key.file_name()),
+            None => return Promise::from_ready(None),
+        };
+        let key = key.clone();
+        Promise::spawn_thread("df_cache_load", move || {
+            let entry = decode(&key, &std::fs::read(&path).ok()?)?;
+            // the marker's modification time records the use, keeping the entry from being pruned
+            let _ = std::fs::write(path.with_extension("used"), []);
+            Some(entry)
+        })
+    }
+
+    pub fn store(&self, key: CacheKey, entry: CachedDataFrame) {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => return,
+        };
+        let budget = self.budget;
+        std::thread::spawn(move || {
+            let name = key.file_name();
+            let path = dir.join(&name);
+            let bytes = match encode(key, &entry) {
+                Some(bytes) => bytes,
+                None => return,
+            };
+            // written aside and renamed, so a crash never leaves a truncated entry behind
+            let partial = path.with_extension("partial");
+            let written = std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&partial, bytes))
+                .and_then(|_| std::fs::rename(&partial, &path))
+                .and_then(|_| prune_dir(&dir, budget, &name));
+            if let Err(e) = written {
+                log::warn!("failed to cache dataframe in {:?}: {}", path, e);
+            }
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+const IDB_NAME: &str = "h_analyzer";
+#[cfg(target_arch = "wasm32")]
+const IDB_STORE: &str = "dataframes";
+/// "<size> <last use in ms>" of each entry, so pruning does not read the entries themselves
+#[cfg(target_arch = "wasm32")]
+const IDB_USAGE_STORE: &str = "usage";
+
+#[cfg(target_arch = "wasm32")]
+async fn open_idb() -> rexie::Result<rexie::Rexie> {
+    rexie::Rexie::builder(IDB_NAME)
+        .version(2)
+        .add_object_store(rexie::ObjectStore::new(IDB_STORE))
+        .add_object_store(rexie::ObjectStore::new(IDB_USAGE_STORE))
+        .build()
+        .await
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn put_usage(transaction: &rexie::Transaction, name: &str, size: u64) -> rexie::Result<()> {
+    let usage = format!("{} {}", size, js_sys::Date::now() as u64);
+    transaction
+        .store(IDB_USAGE_STORE)?
+        .put(
+            &wasm_bindgen::JsValue::from_str(&usage),
+            Some(&wasm_bindgen::JsValue::from_str(name)),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn idb_get(name: String) -> rexie::Result<Option<Vec<u8>>> {
+    let db = open_idb().await?;
+    let transaction = db.transaction(
+        &[IDB_STORE, IDB_USAGE_STORE],
+        rexie::TransactionMode::ReadWrite,
+    )?;
+    let value = transaction
+        .store(IDB_STORE)?
+        .get(&wasm_bindgen::JsValue::from_str(&name))
+        .await?;
+    if value.is_undefined() {
+        return Ok(None);
+    }
+    let bytes = js_sys::Uint8Array::new(&value).to_vec();
+    put_usage(&transaction, &name, bytes.len() as u64).await?;
+    transaction.done().await?;
+    Ok(Some(bytes))
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn idb_put(name: String, bytes: Vec<u8>, budget: u64) -> rexie::Result<()> {
+    let db = open_idb().await?;
+    let transaction = db.transaction(
+        &[IDB_STORE, IDB_USAGE_STORE],
+        rexie::TransactionMode::ReadWrite,
+    )?;
+    let store = transaction.store(IDB_STORE)?;
+    store
+        .put(
+            &js_sys::Uint8Array::from(bytes.as_slice()).into(),
+            Some(&wasm_bindgen::JsValue::from_str(&name)),
+        )
+        .await?;
+    put_usage(&transaction, &name, bytes.len() as u64).await?;
+
+    let usage_store = transaction.store(IDB_USAGE_STORE)?;
+    let mut entries = Vec::new();
+    for (key, usage) in usage_store.get_all(None, None, None, None).await? {
+        let (key, usage) = match (key.as_string(), usage.as_string()) {
+            (Some(key), Some(usage)) => (key, usage),
+            _ => continue,
+        };
+        let mut fields = usage.split(' ').map(str::parse::<u64>);
+        if let (Some(Ok(size)), Some(Ok(last_used))) = (fields.next(), fields.next()) {
+            entries.push((key, size, last_used));
+        }
+    }
+    for removed in entries_over_budget(entries, budget, &name) {
+        let removed = wasm_bindgen::JsValue::from_str(&removed);
+        store.delete(&removed).await?;
+        usage_store.delete(&removed).await?;
+    }
+    transaction.done().await
+}
+
+#[cfg(target_arch = "wasm32")]
+impl DataFrameCache {
+    /// Entries in the IndexedDB of the page's origin.
+    pub fn persistent() -> Self {
+        Self {
+            enabled: true,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+
+    pub fn load(&self, key: &CacheKey) -> Promise<Option<CachedDataFrame>> {
+        if !self.enabled {
+            return Promise::from_ready(None);
+        }
+        let key = key.clone();
+        Promise::spawn_local(async move {
+            let bytes = idb_get(key.file_name()).await.ok()??;
+            decode(&key, &bytes)
+        })
+    }
+
+    pub fn store(&self, key: CacheKey, entry: CachedDataFrame) {
+        if !self.enabled {
+            return;
+        }
+        let name = key.file_name();
+        let bytes = match encode(key, &entry) {
+            Some(bytes) => bytes,
+            None => return,
+        };
+        let budget = self.budget;
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = idb_put(name, bytes, budget).await {
+                log::warn!("failed to cache dataframe: {:?}", e);
+            }
+        });
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_dataframe_is_loaded_under_its_key_only() {
+        let dir = std::env::temp_dir().join(format!("h_analyzer_df_cache_{}", std::process::id()));
+        let cache = DataFrameCache::in_dir(dir.clone());
+        let info = grpc_fs::DataFrameInfo {
+            df_path: "/data/a.csv".to_string(),
+            ..Default::default()
+        };
+        let key = CacheKey::new("http://robot:50051", &info, &DataFrameProjection::default());
+        let entry = CachedDataFrame {
+            fingerprint: "v1".to_string(),
+            df: df!("x" => [1.0, 2.0]).unwrap(),
+        };
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(key.file_name()),
+            encode(key.clone(), &entry).unwrap(),
+        )
+        .unwrap();
+
+        let loaded = cache.load(&key).block_and_take().unwrap();
+        assert_eq!(loaded.fingerprint, "v1");
+        assert!(loaded.df.frame_equal(&entry.df));
+        assert!(dir.join(key.file_name()).with_extension("used").exists());
+        let other = CacheKey::new(
+            "http://replay:50051",
+            &info,
+            &DataFrameProjection::default(),
+        );
+        assert!(cache.load(&other).block_and_take().is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_pruned_first() {
+        let entries = vec![
+            ("a.df".to_string(), 40, 3),
+            ("b.df".to_string(), 40, 1),
+            ("c.df".to_string(), 40, 2),
+            ("d.df".to_string(), 40, 0),
+        ];
+        // d was just stored and stays although it was never used since
+        assert_eq!(
+            entries_over_budget(entries.clone(), 100, "d.df"),
+            vec!["b.df".to_string(), "c.df".to_string()]
+        );
+        assert!(entries_over_budget(entries, 160, "d.df").is_empty());
+    }
+
+    #[test]
+    fn file_name_ignores_the_updated_flag() {
+        let info = |updated| grpc_fs::DataFrameInfo {
+            df_path: "/data/a.csv".to_string(),
+            load_option: Some(grpc_fs::DataFrameLoadOption {
+                updated: updated,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let projection = DataFrameProjection::default();
+        let key = CacheKey::new("http://robot:50051", &info(false), &projection);
+        assert_eq!(
+            key.file_name(),
+            CacheKey::new("http://robot:50051", &info(true), &projection).file_name()
+        );
+    }
+}
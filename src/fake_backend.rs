@@ -0,0 +1,330 @@
+use crate::backend_talk::{
+    grpc_data_transfer, grpc_fs, Backend, DataFrameListChange, DataFrameProjection,
+    FetchedDataFrame,
+};
+use crate::request::{RpcPromise, Subscription, TransferProgress};
+use polars::prelude::*;
+
+#[derive(Default)]
+pub struct FakeBackendState {
+    pub default_path: String,
+    pub directories: std::collections::HashMap<String, grpc_fs::ListResponse>,
+    pub dataframes: std::collections::BTreeMap<usize, (grpc_fs::DataFrameInfo, DataFrame)>,
+    // content fingerprints sent along with dataframes, none are sent for ids missing here
+    pub fingerprints: std::collections::HashMap<usize, String>,
+    pub worlds: std::collections::BTreeMap<String, Vec<h_analyzer_data::WorldFrame>>,
+
+    // requests seen so far, for assertions
+    pub listed_paths: Vec<String>,
+    pub requested_df_ids: Vec<usize>,
+    pub requested_frames: Vec<(String, u32)>,
+    pub lazy_plans: Vec<String>,
+    pub loaded_rosbags: Vec<String>,
+    pub loaded_files: Vec<(String, grpc_fs::DataFrameLoadOption)>,
+    pub saved_df_lists: Vec<Vec<grpc_fs::DataFrameInfo>>,
+
+    // answer to every lazy query, the fake does not evaluate plans
+    pub lazy_result: Option<DataFrame>,
+    // health checks fail while this is set, as if the server were down
+    pub unreachable: bool,
+    // health checks are rejected while this is set, as for wrong credentials
+    pub rejected: bool,
+    // list changes are not streamed while this is set, as by older servers
+    pub without_change_stream: bool,
+    // whole dataframes are sent for every projection while this is set, as by older servers
+    pub without_projections: bool,
+
+    df_list_subscribers: Vec<std::sync::mpsc::Sender<Result<DataFrameListChange, tonic::Status>>>,
+}
+
+impl FakeBackendState {
+    fn notify_df_list(&mut self, change: DataFrameListChange) {
+        self.df_list_subscribers
+            .retain(|sender| sender.send(Ok(change.clone())).is_ok());
+    }
+}
+
+/// In-memory backend answering every rpc immediately from canned data.
+/// Clones share their state, so a test can keep one to modify the "server" while `CommonData` owns another.
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    pub state: std::sync::Arc<std::sync::Mutex<FakeBackendState>>,
+}
+
+impl FakeBackend {
+    pub fn state(&self) -> std::sync::MutexGuard<'_, FakeBackendState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn add_dataframe(&self, id: usize, path: &str, df: DataFrame, updated: bool) {
+        let info = grpc_fs::DataFrameInfo {
+            id: Some(grpc_fs::DataFrameId { id: id as u32 }),
+            df_path: path.to_string(),
+            load_option: Some(grpc_fs::DataFrameLoadOption {
+                updated: updated,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut state = self.state();
+        let change = if state.dataframes.contains_key(&id) {
+            DataFrameListChange::Updated(info.clone())
+        } else {
+            DataFrameListChange::Added(info.clone())
+        };
+        state.dataframes.insert(id, (info, df));
+        state.notify_df_list(change);
+    }
+
+    pub fn add_directory(&self, path: &str, directories: &[&str], files: &[&str]) {
+        let list = grpc_fs::ListResponse {
+            directories: directories.iter().map(|d| d.to_string()).collect(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        };
+        self.state().directories.insert(path.to_string(), list);
+    }
+
+    pub fn push_world_frame(&self, world_name: &str, frame: h_analyzer_data::WorldFrame) {
+        self.state()
+            .worlds
+            .entry(world_name.to_string())
+            .or_default()
+            .push(frame);
+    }
+}
+
+impl Backend for FakeBackend {
+    fn server_address(&self) -> &str {
+        "fake://"
+    }
+
+    fn is_in_process(&self) -> bool {
+        false
+    }
+
+    fn check_health(&self) -> RpcPromise<grpc_fs::Empty> {
+        if self.state().unreachable {
+            RpcPromise::from_ready(Err(tonic::Status::unavailable("fake server is down")))
+        } else if self.state().rejected {
+            RpcPromise::from_ready(Err(tonic::Status::unauthenticated("invalid token")))
+        } else {
+            RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+        }
+    }
+
+    fn supports_projection(&self) -> Option<bool> {
+        Some(!self.state().without_projections)
+    }
+
+    fn load_rosbag2(&self, dirpath: String) -> RpcPromise<grpc_fs::Empty> {
+        self.state().loaded_rosbags.push(dirpath);
+        RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+    }
+
+    fn get_world_list(&self) -> RpcPromise<grpc_data_transfer::WorldMetadataList> {
+        let list = self
+            .state()
+            .worlds
+            .iter()
+            .map(|(name, frames)| grpc_data_transfer::WorldMetadata {
+                id: Some(grpc_data_transfer::WorldId { id: name.clone() }),
+                total_frame_num: frames.len() as u32,
+                ..Default::default()
+            })
+            .collect();
+        RpcPromise::from_ready(Ok(grpc_data_transfer::WorldMetadataList { list: list }))
+    }
+
+    fn request_default_path(&self) -> RpcPromise<grpc_fs::PathMessage> {
+        let path = self.state().default_path.clone();
+        RpcPromise::from_ready(Ok(grpc_fs::PathMessage { path: path }))
+    }
+
+    fn request_list(&self, path: String) -> RpcPromise<grpc_fs::ListResponse> {
+        let mut state = self.state();
+        state.listed_paths.push(path.clone());
+        RpcPromise::from_ready(
+            state
+                .directories
+                .get(&path)
+                .cloned()
+                .ok_or_else(|| tonic::Status::not_found(path)),
+        )
+    }
+
+    fn save_df_list(
+        &self,
+        df_info_list: Vec<grpc_fs::DataFrameInfo>,
+    ) -> RpcPromise<grpc_fs::Empty> {
+        self.state().saved_df_lists.push(df_info_list);
+        RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+    }
+
+    fn request_get_df_list(&self) -> RpcPromise<grpc_fs::DataFrameInfoList> {
+        let list = self
+            .state()
+            .dataframes
+            .values()
+            .map(|(info, _)| info.clone())
+            .collect();
+        RpcPromise::from_ready(Ok(grpc_fs::DataFrameInfoList { list: list }))
+    }
+
+    fn load_df_from_file_request(
+        &self,
+        filepath: String,
+        load_option: grpc_fs::DataFrameLoadOption,
+    ) -> RpcPromise<usize> {
+        let id = {
+            let mut state = self.state();
+            state.loaded_files.push((filepath.clone(), load_option));
+            state.dataframes.keys().max().map_or(0, |id| id + 1)
+        };
+        self.add_dataframe(id, filepath.as_str(), DataFrame::default(), false);
+        RpcPromise::from_ready(Ok(id))
+    }
+
+    fn remove_df_request(&self, id: grpc_fs::DataFrameId) -> RpcPromise<grpc_fs::Empty> {
+        let mut state = self.state();
+        if state.dataframes.remove(&(id.id as usize)).is_some() {
+            state.notify_df_list(DataFrameListChange::Removed(id));
+        }
+        RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+    }
+
+    fn get_df_request(
+        &self,
+        id: grpc_fs::DataFrameId,
+        projection: DataFrameProjection,
+        if_none_match: Option<String>,
+        progress: TransferProgress,
+    ) -> RpcPromise<FetchedDataFrame> {
+        let mut state = self.state();
+        let id = id.id as usize;
+        state.requested_df_ids.push(id);
+        let fingerprint = state.fingerprints.get(&id).cloned();
+        if fingerprint.is_some() && fingerprint == if_none_match {
+            return RpcPromise::from_ready(Ok(FetchedDataFrame {
+                df: None,
+                fingerprint: fingerprint,
+            }));
+        }
+        let projection = if state.without_projections {
+            DataFrameProjection::default()
+        } else {
+            projection
+        };
+        let df = match state.dataframes.get(&id) {
+            Some((_, df)) => match projection.apply(df.clone()) {
+                Ok(df) => Some(df),
+                Err(e) => {
+                    return RpcPromise::from_ready(Err(tonic::Status::invalid_argument(
+                        e.to_string(),
+                    )))
+                }
+            },
+            None => None,
+        };
+        if let Some(df) = &df {
+            // the whole dataframe arrives as a single chunk
+            progress.start(Some(df.estimated_size()));
+            progress.add_chunk(df.estimated_size(), df.estimated_size());
+        }
+        RpcPromise::from_ready(
+            df.map(|df| FetchedDataFrame {
+                df: Some(df),
+                fingerprint: fingerprint,
+            })
+            .ok_or_else(|| tonic::Status::not_found(format!("dataframe {}", id))),
+        )
+    }
+
+    fn run_lazy_query(
+        &self,
+        _id: grpc_fs::DataFrameId,
+        plan: String,
+        _progress: TransferProgress,
+    ) -> RpcPromise<DataFrame> {
+        let mut state = self.state();
+        state.lazy_plans.push(plan);
+        RpcPromise::from_ready(
+            state
+                .lazy_result
+                .clone()
+                .ok_or_else(|| tonic::Status::unimplemented("lazy queries")),
+        )
+    }
+
+    fn get_world_frame(
+        &self,
+        world_name: String,
+        frame_index: u32,
+    ) -> RpcPromise<h_analyzer_data::WorldFrame> {
+        let mut state = self.state();
+        state
+            .requested_frames
+            .push((world_name.clone(), frame_index));
+        let frame = state
+            .worlds
+            .get(&world_name)
+            .and_then(|frames| frames.get(frame_index as usize))
+            .cloned();
+        RpcPromise::from_ready(frame.ok_or_else(|| {
+            tonic::Status::not_found(format!("{} frame {}", world_name, frame_index))
+        }))
+    }
+
+    fn get_world_frames(
+        &self,
+        world_name: String,
+        start_index: u32,
+        count: u32,
+    ) -> RpcPromise<Vec<h_analyzer_data::WorldFrame>> {
+        let mut frames = Vec::new();
+        for idx in start_index..start_index.saturating_add(count) {
+            match self.get_world_frame(world_name.clone(), idx).ready() {
+                Some(Ok(frame)) => frames.push(frame.clone()),
+                _ => {
+                    return RpcPromise::from_ready(Err(tonic::Status::not_found(format!(
+                        "{} frame {}",
+                        world_name, idx
+                    ))))
+                }
+            }
+        }
+        RpcPromise::from_ready(Ok(frames))
+    }
+
+    fn subscribe_world_frames(
+        &self,
+        world_name: String,
+        start_index: u32,
+    ) -> Subscription<h_analyzer_data::WorldFrame> {
+        // hands out the frames existing at the time of subscribing, then the stream ends
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (abort_handle, _) = futures_util::future::AbortHandle::new_pair();
+        if let Some(frames) = self.state().worlds.get(&world_name) {
+            for frame in frames.iter().skip(start_index as usize) {
+                let _ = sender.send(Ok(frame.clone()));
+            }
+        }
+        Subscription::new(receiver, abort_handle)
+    }
+
+    fn subscribe_df_list(&self) -> Subscription<DataFrameListChange> {
+        // changes made through the fake are passed on as they happen
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (abort_handle, _) = futures_util::future::AbortHandle::new_pair();
+        let mut state = self.state();
+        if state.without_change_stream {
+            let _ = sender.send(Err(tonic::Status::unimplemented("WatchDataFrameList")));
+            return Subscription::new(receiver, abort_handle);
+        }
+        for (info, _) in state.dataframes.values() {
+            let _ = sender.send(Ok(DataFrameListChange::Added(info.clone())));
+        }
+        state.df_list_subscribers.push(sender);
+        Subscription::new(receiver, abort_handle)
+    }
+}
@@ -4,6 +4,17 @@ mod backend_talk;
 mod components;
 pub use app::TemplateApp;
 mod common_data;
+mod csv_file;
+mod df_cache;
+#[cfg(test)]
+mod fake_backend;
+#[cfg(not(target_arch = "wasm32"))]
+mod local_backend;
+mod payload;
+mod request;
+mod rpc_log;
+mod session;
+mod workspace;
 
 #[macro_use]
 mod macros;
@@ -0,0 +1,283 @@
+//! Data source for the native build which works without an analyzer server.
+//! Directories are listed with `std::fs` and csv files are read by polars in this process.
+
+use crate::backend_talk::{
+    grpc_data_transfer, grpc_fs, Backend, DataFrameListChange, DataFrameProjection,
+    FetchedDataFrame,
+};
+use crate::csv_file;
+use crate::request::{RpcPromise, Subscription, TransferProgress};
+use polars::prelude::*;
+
+/// Connection profiles pointing here use the local backend instead of a server.
+pub const LOCAL_ADDRESS: &str = "local://";
+
+#[derive(Default)]
+struct LocalBackendState {
+    next_id: usize,
+    dataframes: std::collections::BTreeMap<usize, (grpc_fs::DataFrameInfo, DataFrame)>,
+    df_list_subscribers: Vec<std::sync::mpsc::Sender<Result<DataFrameListChange, tonic::Status>>>,
+}
+
+impl LocalBackendState {
+    fn notify_df_list(&mut self, change: DataFrameListChange) {
+        self.df_list_subscribers
+            .retain(|sender| sender.send(Ok(change.clone())).is_ok());
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LocalBackend {
+    state: std::sync::Arc<std::sync::Mutex<LocalBackendState>>,
+}
+
+/// Runs `job` on a thread of its own, so that reading a large file does not stall the ui.
+/// Cancelling only drops the result, the file is still read to the end.
+fn run_blocking<T: Send + 'static>(
+    job: impl FnOnce() -> Result<T, tonic::Status> + Send + 'static,
+) -> RpcPromise<T> {
+    let (abort_handle, _) = futures_util::future::AbortHandle::new_pair();
+    RpcPromise::new(
+        poll_promise::Promise::spawn_thread("local_backend", job),
+        abort_handle,
+    )
+}
+
+fn list_directory(path: &str) -> std::io::Result<grpc_fs::ListResponse> {
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            directories.push(name);
+        } else {
+            files.push(name);
+        }
+    }
+    directories.sort();
+    files.sort();
+    Ok(grpc_fs::ListResponse {
+        directories: directories,
+        files: files,
+    })
+}
+
+impl LocalBackend {
+    fn state(&self) -> std::sync::MutexGuard<'_, LocalBackendState> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Backend for LocalBackend {
+    fn server_address(&self) -> &str {
+        LOCAL_ADDRESS
+    }
+
+    fn is_in_process(&self) -> bool {
+        true
+    }
+
+    fn check_health(&self) -> RpcPromise<grpc_fs::Empty> {
+        RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+    }
+
+    fn supports_projection(&self) -> Option<bool> {
+        Some(true)
+    }
+
+    fn load_rosbag2(&self, _dirpath: String) -> RpcPromise<grpc_fs::Empty> {
+        RpcPromise::from_ready(Err(tonic::Status::unimplemented(
+            "rosbag2 files are only read by the analyzer server",
+        )))
+    }
+
+    fn get_world_list(&self) -> RpcPromise<grpc_data_transfer::WorldMetadataList> {
+        // worlds are produced by running analyzers, there are none offline
+        RpcPromise::from_ready(Ok(grpc_data_transfer::WorldMetadataList {
+            list: Vec::new(),
+        }))
+    }
+
+    fn request_default_path(&self) -> RpcPromise<grpc_fs::PathMessage> {
+        RpcPromise::from_ready(
+            std::env::current_dir()
+                .map(|path| grpc_fs::PathMessage {
+                    path: path.to_string_lossy().to_string(),
+                })
+                .map_err(|e| tonic::Status::internal(e.to_string())),
+        )
+    }
+
+    fn request_list(&self, path: String) -> RpcPromise<grpc_fs::ListResponse> {
+        RpcPromise::from_ready(
+            list_directory(&path).map_err(|e| tonic::Status::not_found(format!("{}: {}", path, e))),
+        )
+    }
+
+    fn save_df_list(
+        &self,
+        _df_info_list: Vec<grpc_fs::DataFrameInfo>,
+    ) -> RpcPromise<grpc_fs::Empty> {
+        RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+    }
+
+    fn request_get_df_list(&self) -> RpcPromise<grpc_fs::DataFrameInfoList> {
+        let list = self
+            .state()
+            .dataframes
+            .values()
+            .map(|(info, _)| info.clone())
+            .collect();
+        RpcPromise::from_ready(Ok(grpc_fs::DataFrameInfoList { list: list }))
+    }
+
+    fn load_df_from_file_request(
+        &self,
+        filepath: String,
+        load_option: grpc_fs::DataFrameLoadOption,
+    ) -> RpcPromise<usize> {
+        if load_option.source_type != grpc_fs::DataFrameSourceType::Csv as i32 {
+            return RpcPromise::from_ready(Err(tonic::Status::unimplemented(
+                "only csv files can be loaded without the analyzer server",
+            )));
+        }
+        let state = self.state.clone();
+        run_blocking(move || {
+            let contents = std::fs::read(&filepath)
+                .map_err(|e| tonic::Status::not_found(format!("{}: {}", filepath, e)))?;
+            let df = csv_file::read_csv(&contents, &load_option)
+                .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
+            let mut state = state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            let info = grpc_fs::DataFrameInfo {
+                id: Some(grpc_fs::DataFrameId { id: id as u32 }),
+                df_path: filepath,
+                load_option: Some(load_option),
+                ..Default::default()
+            };
+            state.dataframes.insert(id, (info.clone(), df));
+            state.notify_df_list(DataFrameListChange::Added(info));
+            Ok(id)
+        })
+    }
+
+    fn remove_df_request(&self, id: grpc_fs::DataFrameId) -> RpcPromise<grpc_fs::Empty> {
+        let mut state = self.state();
+        if state.dataframes.remove(&(id.id as usize)).is_some() {
+            state.notify_df_list(DataFrameListChange::Removed(id));
+        }
+        RpcPromise::from_ready(Ok(grpc_fs::Empty {}))
+    }
+
+    fn get_df_request(
+        &self,
+        id: grpc_fs::DataFrameId,
+        projection: DataFrameProjection,
+        _if_none_match: Option<String>,
+        progress: TransferProgress,
+    ) -> RpcPromise<FetchedDataFrame> {
+        let df = match self.state().dataframes.get(&(id.id as usize)) {
+            Some((_, df)) => df.clone(),
+            None => {
+                return RpcPromise::from_ready(Err(tonic::Status::not_found(format!(
+                    "dataframe {}",
+                    id.id
+                ))))
+            }
+        };
+        let df = match projection.apply(df) {
+            Ok(df) => df,
+            Err(e) => {
+                return RpcPromise::from_ready(Err(tonic::Status::invalid_argument(e.to_string())))
+            }
+        };
+        // nothing is transferred, the columns are shared with the loaded dataframe
+        progress.start(Some(df.estimated_size()));
+        progress.add_chunk(df.estimated_size(), df.estimated_size());
+        // local files are read anew each session, so they are never cached
+        RpcPromise::from_ready(Ok(FetchedDataFrame {
+            df: Some(df),
+            fingerprint: None,
+        }))
+    }
+
+    fn run_lazy_query(
+        &self,
+        _id: grpc_fs::DataFrameId,
+        _plan: String,
+        _progress: TransferProgress,
+    ) -> RpcPromise<DataFrame> {
+        // `is_in_process` makes the frontend collect queries on local files itself
+        RpcPromise::from_ready(Err(tonic::Status::unimplemented(
+            "queries on local files are evaluated by the frontend",
+        )))
+    }
+
+    fn get_world_frame(
+        &self,
+        world_name: String,
+        _frame_index: u32,
+    ) -> RpcPromise<h_analyzer_data::WorldFrame> {
+        RpcPromise::from_ready(Err(tonic::Status::not_found(world_name)))
+    }
+
+    fn get_world_frames(
+        &self,
+        world_name: String,
+        _start_index: u32,
+        _count: u32,
+    ) -> RpcPromise<Vec<h_analyzer_data::WorldFrame>> {
+        RpcPromise::from_ready(Err(tonic::Status::not_found(world_name)))
+    }
+
+    fn subscribe_world_frames(
+        &self,
+        world_name: String,
+        _start_index: u32,
+    ) -> Subscription<h_analyzer_data::WorldFrame> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (abort_handle, _) = futures_util::future::AbortHandle::new_pair();
+        let _ = sender.send(Err(tonic::Status::not_found(world_name)));
+        Subscription::new(receiver, abort_handle)
+    }
+
+    fn subscribe_df_list(&self) -> Subscription<DataFrameListChange> {
+        // every change goes through this backend, so it is passed on as it happens
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (abort_handle, _) = futures_util::future::AbortHandle::new_pair();
+        let mut state = self.state();
+        for (info, _) in state.dataframes.values() {
+            let _ = sender.send(Ok(DataFrameListChange::Added(info.clone())));
+        }
+        state.df_list_subscribers.push(sender);
+        Subscription::new(receiver, abort_handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("h_analyzer_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn directories_are_listed_apart_from_files() {
+        let dir = scratch_dir("list");
+        std::fs::create_dir_all(dir.join("logs")).unwrap();
+        std::fs::write(dir.join("b.csv"), "x\n1\n").unwrap();
+        std::fs::write(dir.join("a.csv"), "x\n1\n").unwrap();
+
+        let backend = LocalBackend::default();
+        let listed = backend.request_list(dir.to_string_lossy().to_string());
+        let listed = listed.ready().unwrap().as_ref().unwrap();
+        assert_eq!(listed.directories, vec!["logs".to_string()]);
+        assert_eq!(listed.files, vec!["a.csv".to_string(), "b.csv".to_string()]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
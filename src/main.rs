@@ -6,6 +6,20 @@
 fn main() -> eframe::Result<()> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    // `--server http://host:50051` overrides the persisted connection profile,
+    // `--local` works on the files of this machine without any server
+    let mut args = std::env::args().skip(1);
+    let mut server_override = None;
+    while let Some(arg) = args.next() {
+        if arg == "--local" {
+            server_override = Some("local://".to_string());
+        } else if arg == "--server" {
+            server_override = args.next();
+        } else if let Some(addr) = arg.strip_prefix("--server=") {
+            server_override = Some(addr.to_string());
+        }
+    }
+
     let native_options = eframe::NativeOptions {
         initial_window_size: Some([1200.0, 1000.0].into()),
         initial_window_pos: None,
@@ -16,7 +30,7 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "eframe template",
         native_options,
-        Box::new(|cc| Box::new(eframe_template::TemplateApp::new(cc))),
+        Box::new(|cc| Box::new(eframe_template::TemplateApp::new(cc, server_override))),
     )
 }
 
@@ -33,7 +47,7 @@ fn main() {
             .start(
                 "the_canvas_id", // hardcode it
                 web_options,
-                Box::new(|cc| Box::new(eframe_template::TemplateApp::new(cc))),
+                Box::new(|cc| Box::new(eframe_template::TemplateApp::new(cc, None))),
             )
             .await
             .expect("failed to start eframe");
@@ -0,0 +1,448 @@
+//! Framing of the serialized data sent through the byte streaming rpcs.
+//!
+//! A payload starts with `MAGIC` followed by the format version as little endian u16.
+//! Backends predating the header send the bare bincode body, which is accepted as version 0
+//! as long as it still decodes.
+//!
+//! DataFrames may also come as an Arrow IPC stream instead, recognized by the continuation marker
+//! every IPC message starts with. Chunks may split the stream anywhere, so every record batch is
+//! decoded as soon as its message is complete and stacked onto the ones before, only an unfinished
+//! message is kept around. Several complete streams in a row, as sent by backends which wrote one
+//! per chunk, are read in turn.
+//!
+//! Independent of the format, the server may compress every chunk on its own with one of the
+//! encodings offered by the frontend.
+
+use polars::prelude::*;
+
+pub const MAGIC: &[u8; 4] = b"HAPL";
+/// Version of the serialized types this frontend was built against.
+pub const FORMAT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 2;
+const ARROW_CONTINUATION: &[u8; 4] = &[0xff, 0xff, 0xff, 0xff];
+
+/// Sent along with dataframe requests, most preferred first.
+pub const ACCEPTED_DF_FORMATS: &str = "arrow-ipc-stream, bincode";
+/// Chunk compressions this frontend can undo, most preferred first.
+pub const ACCEPTED_ENCODINGS: &str = "zstd, lz4, identity";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Identity,
+    Zstd,
+    Lz4,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Self, DecodeError> {
+        match name.trim() {
+            "" | "identity" => Ok(Encoding::Identity),
+            "zstd" => Ok(Encoding::Zstd),
+            "lz4" => Ok(Encoding::Lz4),
+            other => Err(DecodeError::UnsupportedEncoding(other.to_string())),
+        }
+    }
+
+    pub fn decompress(&self, chunk: Vec<u8>) -> Result<Vec<u8>, DecodeError> {
+        use std::io::Read;
+        let mut raw = Vec::new();
+        match self {
+            Encoding::Identity => return Ok(chunk),
+            Encoding::Zstd => {
+                let mut decoder = ruzstd::StreamingDecoder::new(chunk.as_slice())
+                    .map_err(|e| DecodeError::Decompression(format!("{:?}", e)))?;
+                decoder.read_to_end(&mut raw)
+            }
+            Encoding::Lz4 => {
+                lz4_flex::frame::FrameDecoder::new(chunk.as_slice()).read_to_end(&mut raw)
+            }
+        }
+        .map_err(|e| DecodeError::Decompression(e.to_string()))?;
+        Ok(raw)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// the backend serializes a different revision of the shared data types
+    Incompatible {
+        found: u16,
+    },
+    /// the body does not decode as the expected type
+    Malformed {
+        version: u16,
+        reason: String,
+    },
+    /// an arrow ipc chunk could not be read
+    MalformedArrow {
+        reason: String,
+    },
+    /// the server compressed the payload with something that was not offered
+    UnsupportedEncoding(String),
+    Decompression(String),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Incompatible { found } => write!(
+                f,
+                "frontend/backend incompatible: backend sends payload format {}, frontend expects {}",
+                found, FORMAT_VERSION
+            ),
+            DecodeError::Malformed { version: 0, reason } => write!(
+                f,
+                "frontend/backend incompatible: undecodable payload without version header ({})",
+                reason
+            ),
+            DecodeError::Malformed { version, reason } => {
+                write!(f, "malformed payload of format {}: {}", version, reason)
+            }
+            DecodeError::MalformedArrow { reason } => {
+                write!(f, "malformed arrow ipc payload: {}", reason)
+            }
+            DecodeError::UnsupportedEncoding(name) => {
+                write!(f, "frontend/backend incompatible: unknown payload encoding {}", name)
+            }
+            DecodeError::Decompression(reason) => {
+                write!(f, "failed to decompress payload: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for tonic::Status {
+    fn from(e: DecodeError) -> Self {
+        match e {
+            DecodeError::Incompatible { .. }
+            | DecodeError::Malformed { version: 0, .. }
+            | DecodeError::UnsupportedEncoding(_) => {
+                tonic::Status::failed_precondition(e.to_string())
+            }
+            DecodeError::Malformed { .. }
+            | DecodeError::MalformedArrow { .. }
+            | DecodeError::Decompression(_) => tonic::Status::data_loss(e.to_string()),
+        }
+    }
+}
+
+/// Format version and body of a payload.
+pub fn split_header(bytes: &[u8]) -> (u16, &[u8]) {
+    if bytes.len() >= HEADER_LEN && bytes.starts_with(MAGIC) {
+        let version = u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+        (version, &bytes[HEADER_LEN..])
+    } else {
+        (0, bytes)
+    }
+}
+
+pub fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let (version, body) = split_header(bytes);
+    if version != 0 && version != FORMAT_VERSION {
+        return Err(DecodeError::Incompatible { found: version });
+    }
+    bincode::deserialize(body).map_err(|e| DecodeError::Malformed {
+        version: version,
+        reason: e.to_string(),
+    })
+}
+
+fn is_arrow_ipc(chunk: &[u8]) -> bool {
+    chunk.starts_with(ARROW_CONTINUATION)
+}
+
+fn malformed_arrow(reason: impl ToString) -> DecodeError {
+    DecodeError::MalformedArrow {
+        reason: reason.to_string(),
+    }
+}
+
+fn le_bytes<const N: usize>(buf: &[u8], at: usize) -> Option<[u8; N]> {
+    buf.get(at..at.checked_add(N)?)?.try_into().ok()
+}
+
+/// Header type and body length of the flatbuffer `Message` of an ipc message, fields 1 and 3 of
+/// its root table.
+fn message_header(metadata: &[u8]) -> Option<(u8, usize)> {
+    let table = u32::from_le_bytes(le_bytes(metadata, 0)?) as usize;
+    let vtable_offset = i32::from_le_bytes(le_bytes(metadata, table)?) as isize;
+    let vtable = usize::try_from(table as isize - vtable_offset).ok()?;
+    let vtable_len = u16::from_le_bytes(le_bytes(metadata, vtable)?) as usize;
+    // position of a field within the table, 0 if it was left at its default
+    let field = |index: usize| -> Option<usize> {
+        let entry = 4 + 2 * index;
+        if entry + 2 > vtable_len {
+            return Some(0);
+        }
+        Some(u16::from_le_bytes(le_bytes(metadata, vtable + entry)?) as usize)
+    };
+    let header_type = match field(1)? {
+        0 => 0,
+        offset => le_bytes::<1>(metadata, table + offset)?[0],
+    };
+    let body_len = match field(3)? {
+        0 => 0,
+        offset => i64::from_le_bytes(le_bytes(metadata, table + offset)?),
+    };
+    Some((header_type, usize::try_from(body_len).ok()?))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum IpcMessage {
+    Schema,
+    DictionaryBatch,
+    RecordBatch,
+    EndOfStream,
+}
+
+/// Length and kind of the ipc message `bytes` start with, `None` until all of it has arrived.
+fn next_ipc_message(bytes: &[u8]) -> Result<Option<(usize, IpcMessage)>, DecodeError> {
+    let prefix_len = ARROW_CONTINUATION.len() + 4;
+    if bytes.len() >= ARROW_CONTINUATION.len() && !is_arrow_ipc(bytes) {
+        return Err(malformed_arrow("missing continuation marker"));
+    }
+    let metadata_len = match le_bytes(bytes, ARROW_CONTINUATION.len()) {
+        Some(len) => i32::from_le_bytes(len),
+        None => return Ok(None),
+    };
+    let metadata_len = usize::try_from(metadata_len)
+        .map_err(|_| malformed_arrow(format!("metadata length {}", metadata_len)))?;
+    if metadata_len == 0 {
+        return Ok(Some((prefix_len, IpcMessage::EndOfStream)));
+    }
+    let metadata = match bytes.get(prefix_len..prefix_len + metadata_len) {
+        Some(metadata) => metadata,
+        None => return Ok(None),
+    };
+    let (header_type, body_len) =
+        message_header(metadata).ok_or_else(|| malformed_arrow("unreadable message header"))?;
+    let message = match header_type {
+        1 => IpcMessage::Schema,
+        2 => IpcMessage::DictionaryBatch,
+        3 => IpcMessage::RecordBatch,
+        other => {
+            return Err(malformed_arrow(format!(
+                "unexpected message type {}",
+                other
+            )))
+        }
+    };
+    let message_len = prefix_len + metadata_len + body_len;
+    if bytes.len() < message_len {
+        return Ok(None);
+    }
+    Ok(Some((message_len, message)))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PayloadFormat {
+    Bincode,
+    ArrowIpc,
+}
+
+/// Turns the chunks of a dataframe transfer into a `DataFrame`, whichever format the backend chose.
+#[derive(Default)]
+pub struct DataFrameDecoder {
+    // decided once the first bytes have arrived, which tell apart ipc from bincode
+    format: Option<PayloadFormat>,
+    // the whole payload for bincode, only the unfinished message for arrow
+    bytes: Vec<u8>,
+    // schema and dictionaries of the current ipc stream, read along with each of its record batches
+    preamble: Vec<u8>,
+    stacked: Option<DataFrame>,
+}
+
+impl DataFrameDecoder {
+    pub fn push(&mut self, chunk: Vec<u8>) -> Result<(), DecodeError> {
+        self.bytes.extend(chunk);
+        if self.format.is_none() && self.bytes.len() >= ARROW_CONTINUATION.len() {
+            self.format = Some(if is_arrow_ipc(&self.bytes) {
+                PayloadFormat::ArrowIpc
+            } else {
+                PayloadFormat::Bincode
+            });
+        }
+        if self.format == Some(PayloadFormat::ArrowIpc) {
+            self.decode_ipc_messages()?;
+        }
+        Ok(())
+    }
+
+    /// Take every complete message off `bytes`, decoding the record batches among them.
+    fn decode_ipc_messages(&mut self) -> Result<(), DecodeError> {
+        let mut start = 0;
+        while let Some((len, message)) = next_ipc_message(&self.bytes[start..])? {
+            let bytes = &self.bytes[start..start + len];
+            start += len;
+            match message {
+                IpcMessage::Schema => self.preamble = bytes.to_vec(),
+                IpcMessage::DictionaryBatch => self.preamble.extend_from_slice(bytes),
+                IpcMessage::RecordBatch => {
+                    if self.preamble.is_empty() {
+                        return Err(malformed_arrow("record batch before the schema"));
+                    }
+                    // the reader stops at the end of the input, no end-of-stream marker needed
+                    let mut batch = self.preamble.clone();
+                    batch.extend_from_slice(bytes);
+                    let df = IpcStreamReader::new(std::io::Cursor::new(batch))
+                        .finish()
+                        .map_err(malformed_arrow)?;
+                    match &mut self.stacked {
+                        Some(stacked) => {
+                            stacked.vstack_mut(&df).map_err(malformed_arrow)?;
+                        }
+                        None => self.stacked = Some(df),
+                    }
+                }
+                IpcMessage::EndOfStream => self.preamble.clear(),
+            }
+        }
+        self.bytes.drain(..start);
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<DataFrame, DecodeError> {
+        match self.format {
+            Some(PayloadFormat::ArrowIpc) => {
+                if !self.bytes.is_empty() {
+                    return Err(malformed_arrow("truncated ipc message"));
+                }
+                // a stream of a schema alone is an empty dataframe
+                let mut df = match self.stacked {
+                    Some(df) => df,
+                    None if !self.preamble.is_empty() => {
+                        IpcStreamReader::new(std::io::Cursor::new(self.preamble))
+                            .finish()
+                            .map_err(malformed_arrow)?
+                    }
+                    None => return Err(malformed_arrow("no ipc stream")),
+                };
+                df.align_chunks();
+                Ok(df)
+            }
+            // too short to tell, which only a bincode payload can be
+            Some(PayloadFormat::Bincode) | None => decode(&self.bytes),
+        }
+    }
+}
+
+/// What a backend is expected to send, only needed here to build test payloads.
+#[cfg(test)]
+pub fn encode<T: serde::Serialize>(value: &T) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend(bincode::serialize(value).unwrap_or_default());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versioned_and_legacy_payloads_decode() {
+        let frame = vec![1.0f64, 2.0, 3.0];
+        assert_eq!(decode::<Vec<f64>>(&encode(&frame)), Ok(frame.clone()));
+        let legacy = bincode::serialize(&frame).unwrap();
+        assert_eq!(decode::<Vec<f64>>(&legacy), Ok(frame));
+    }
+
+    #[test]
+    fn newer_format_is_reported_as_incompatible() {
+        let mut bytes = encode(&vec![1u8]);
+        bytes[MAGIC.len()] = 7;
+        let err = decode::<Vec<u8>>(&bytes).unwrap_err();
+        assert_eq!(err, DecodeError::Incompatible { found: 7 });
+        let status: tonic::Status = err.into();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(status.message().contains("incompatible"));
+    }
+
+    #[test]
+    fn lz4_chunks_are_decompressed() {
+        use std::io::Write;
+        let raw = encode(&vec![0u32; 1000]);
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(&raw).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < raw.len());
+
+        let encoding = Encoding::parse("lz4").unwrap();
+        assert_eq!(encoding.decompress(compressed), Ok(raw));
+        assert_eq!(
+            Encoding::parse("brotli"),
+            Err(DecodeError::UnsupportedEncoding("brotli".to_string()))
+        );
+    }
+
+    #[test]
+    fn zstd_chunks_are_decompressed() {
+        let raw = encode(&vec![0u32; 1000]);
+        // a single raw block, ruzstd only decodes
+        let mut compressed = vec![0x28, 0xb5, 0x2f, 0xfd];
+        // frame header: single segment, content size in two bytes
+        compressed.push(0x60);
+        compressed.extend_from_slice(&((raw.len() - 256) as u16).to_le_bytes());
+        // last block, raw, its size in the upper 21 bits
+        let block_header = 1 | ((raw.len() as u32) << 3);
+        compressed.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        compressed.extend_from_slice(&raw);
+
+        let encoding = Encoding::parse("zstd").unwrap();
+        assert_eq!(encoding.decompress(compressed), Ok(raw));
+        assert!(matches!(
+            encoding.decompress(vec![1, 2, 3]),
+            Err(DecodeError::Decompression(_))
+        ));
+    }
+
+    #[test]
+    fn arrow_stream_split_anywhere_is_read() {
+        let mut df = df!("x" => [1.0, 2.0]).unwrap();
+        // two record batches
+        df.vstack_mut(&df!("x" => [3.0]).unwrap()).unwrap();
+        let mut stream = Vec::new();
+        IpcStreamWriter::new(&mut stream).finish(&mut df).unwrap();
+
+        for chunk_len in [1, 7, 64, stream.len()] {
+            let mut decoder = DataFrameDecoder::default();
+            for chunk in stream.chunks(chunk_len) {
+                decoder.push(chunk.to_vec()).unwrap();
+            }
+            let decoded = decoder.finish().unwrap();
+            assert!(decoded.frame_equal(&df));
+        }
+    }
+
+    #[test]
+    fn record_batches_are_decoded_as_they_arrive() {
+        let mut df = df!("x" => [1.0, 2.0]).unwrap();
+        df.vstack_mut(&df!("x" => [3.0]).unwrap()).unwrap();
+        let mut stream = Vec::new();
+        IpcStreamWriter::new(&mut stream).finish(&mut df).unwrap();
+
+        // everything but the end-of-stream marker, which is all that stays buffered
+        let mut decoder = DataFrameDecoder::default();
+        decoder.push(stream[..stream.len() - 1].to_vec()).unwrap();
+        assert_eq!(decoder.stacked.as_ref().map(|df| df.height()), Some(3));
+        assert_eq!(decoder.bytes.len(), 7);
+        decoder.push(stream[stream.len() - 1..].to_vec()).unwrap();
+        assert!(decoder.finish().unwrap().frame_equal(&df));
+    }
+
+    #[test]
+    fn arrow_chunks_are_stacked() {
+        let mut decoder = DataFrameDecoder::default();
+        for mut part in [df!("x" => [1.0, 2.0]).unwrap(), df!("x" => [3.0]).unwrap()] {
+            let mut chunk = Vec::new();
+            IpcStreamWriter::new(&mut chunk).finish(&mut part).unwrap();
+            decoder.push(chunk).unwrap();
+        }
+        let df = decoder.finish().unwrap();
+        assert_eq!(df.shape(), (3, 1));
+        assert_eq!(df.column("x").unwrap().f64().unwrap().get(2), Some(3.0));
+    }
+}
@@ -0,0 +1,412 @@
+use futures_util::future::{AbortHandle, Either};
+use poll_promise::Promise;
+
+/// `Send` on native, where requests run on the tokio runtime, and nothing on the web,
+/// where they stay on the browser's single thread.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RequestPolicy {
+    /// deadline of a single attempt, for transfers the longest pause between two chunks
+    pub timeout: std::time::Duration,
+    /// number of attempts after the first one, only for idempotent rpcs
+    pub retries: u32,
+    /// wait before the first retry, doubled for every further one
+    pub backoff: std::time::Duration,
+}
+
+impl RequestPolicy {
+    pub const fn idempotent(timeout_secs: u64, retries: u32) -> Self {
+        Self {
+            timeout: std::time::Duration::from_secs(timeout_secs),
+            retries: retries,
+            backoff: std::time::Duration::from_millis(200),
+        }
+    }
+
+    pub const fn once(timeout_secs: u64) -> Self {
+        Self {
+            timeout: std::time::Duration::from_secs(timeout_secs),
+            retries: 0,
+            backoff: std::time::Duration::ZERO,
+        }
+    }
+}
+
+/// Only failures that may go away by themselves are worth another attempt.
+fn is_transient(status: &tonic::Status) -> bool {
+    matches!(
+        status.code(),
+        tonic::Code::Unavailable
+            | tonic::Code::DeadlineExceeded
+            | tonic::Code::ResourceExhausted
+            | tonic::Code::Aborted
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: std::time::Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: std::time::Duration) {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                &resolve,
+                duration.as_millis() as i32,
+            );
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+pub async fn with_timeout<T>(
+    duration: std::time::Duration,
+    future: impl std::future::Future<Output = Result<T, tonic::Status>>,
+) -> Result<T, tonic::Status> {
+    let future = Box::pin(future);
+    let timer = Box::pin(sleep(duration));
+    match futures_util::future::select(future, timer).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(tonic::Status::deadline_exceeded(format!(
+            "no response within {:?}",
+            duration
+        ))),
+    }
+}
+
+/// Like `with_timeout`, but only times out after `duration` without a chunk of `progress` arriving,
+/// so that large transfers over slow links are not cut off while data still flows.
+pub async fn with_idle_timeout<T>(
+    duration: std::time::Duration,
+    progress: &TransferProgress,
+    future: impl std::future::Future<Output = Result<T, tonic::Status>>,
+) -> Result<T, tonic::Status> {
+    progress.touch();
+    let future = Box::pin(future);
+    let watchdog = Box::pin(async {
+        loop {
+            let idle = progress.idle_time();
+            if idle >= duration {
+                break;
+            }
+            sleep(duration - idle).await;
+        }
+    });
+    match futures_util::future::select(future, watchdog).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(tonic::Status::deadline_exceeded(format!(
+            "nothing received for {:?}",
+            duration
+        ))),
+    }
+}
+
+/// Run `attempt` under `policy`, retrying transient failures with exponential backoff.
+/// With `progress`, the timeout applies to the pauses between chunks, and a transfer which
+/// already received some of them is not started over.
+pub async fn run<T, F, Fut>(
+    policy: RequestPolicy,
+    progress: Option<TransferProgress>,
+    attempt: F,
+) -> Result<T, tonic::Status>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, tonic::Status>>,
+{
+    let mut backoff = policy.backoff;
+    let mut tries = 0;
+    loop {
+        let result = match &progress {
+            Some(progress) => with_idle_timeout(policy.timeout, progress, attempt()).await,
+            None => with_timeout(policy.timeout, attempt()).await,
+        };
+        let made_progress = progress.as_ref().map_or(false, |p| p.stats().chunks > 0);
+        match result {
+            Err(status) if is_transient(&status) && tries < policy.retries && !made_progress => {
+                log::debug!("retrying in {:?} after {}", backoff, status);
+                sleep(backoff).await;
+                backoff *= 2;
+                tries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `future` resolving to `Code::Cancelled` once the returned handle is aborted.
+pub fn cancellable<T>(
+    future: impl std::future::Future<Output = Result<T, tonic::Status>>,
+) -> (
+    impl std::future::Future<Output = Result<T, tonic::Status>>,
+    AbortHandle,
+) {
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let future = futures_util::future::Abortable::new(future, abort_registration);
+    let future = async move {
+        future
+            .await
+            .unwrap_or_else(|_| Err(tonic::Status::cancelled("request cancelled")))
+    };
+    (future, abort_handle)
+}
+
+/// Pending result of an rpc which can be cancelled before it completes.
+pub struct RpcPromise<T: Send + 'static> {
+    promise: Promise<Result<T, tonic::Status>>,
+    abort_handle: Option<AbortHandle>,
+}
+
+impl<T: Send + 'static> RpcPromise<T> {
+    pub fn new(promise: Promise<Result<T, tonic::Status>>, abort_handle: AbortHandle) -> Self {
+        Self {
+            promise: promise,
+            abort_handle: Some(abort_handle),
+        }
+    }
+
+    pub fn from_ready(result: Result<T, tonic::Status>) -> Self {
+        Self {
+            promise: Promise::from_ready(result),
+            abort_handle: None,
+        }
+    }
+
+    pub fn ready(&self) -> Option<&Result<T, tonic::Status>> {
+        self.promise.ready()
+    }
+
+    /// Stop waiting for the response. The promise then resolves to `Code::Cancelled`.
+    pub fn cancel(&self) {
+        if let Some(abort_handle) = &self.abort_handle {
+            abort_handle.abort();
+        }
+    }
+}
+
+/// Open ended sequence of rpc results, e.g. frames of a world as they are produced.
+/// Dropping the subscription stops the task feeding it.
+pub struct Subscription<T> {
+    receiver: std::sync::mpsc::Receiver<Result<T, tonic::Status>>,
+    abort_handle: AbortHandle,
+}
+
+impl<T> Subscription<T> {
+    pub fn new(
+        receiver: std::sync::mpsc::Receiver<Result<T, tonic::Status>>,
+        abort_handle: AbortHandle,
+    ) -> Self {
+        Self {
+            receiver: receiver,
+            abort_handle: abort_handle,
+        }
+    }
+
+    /// Everything received since the last call, without blocking.
+    pub fn drain(&self) -> Vec<Result<T, tonic::Status>> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferStats {
+    /// as received, possibly compressed
+    pub bytes: usize,
+    /// after decompression
+    pub raw_bytes: usize,
+    pub chunks: usize,
+    /// only known if the server announces the payload size
+    pub expected_bytes: Option<usize>,
+}
+
+impl TransferStats {
+    /// Decompressed size per received byte, 1.0 for uncompressed transfers.
+    pub fn compression_ratio(&self) -> f32 {
+        if self.bytes == 0 {
+            1.0
+        } else {
+            self.raw_bytes as f32 / self.bytes as f32
+        }
+    }
+}
+
+#[derive(Default)]
+struct ProgressState {
+    stats: TransferStats,
+    // when the attempt started or the last chunk arrived
+    last_activity: Option<web_time::Instant>,
+}
+
+/// Shared view on a running chunked transfer, updated by the task receiving it.
+#[derive(Clone, Default)]
+pub struct TransferProgress(std::sync::Arc<std::sync::Mutex<ProgressState>>);
+
+impl TransferProgress {
+    pub fn stats(&self) -> TransferStats {
+        self.0.lock().map(|s| s.stats).unwrap_or_default()
+    }
+
+    /// Called at the beginning of every attempt, so a retry starts from zero again.
+    pub fn start(&self, expected_bytes: Option<usize>) {
+        if let Ok(mut state) = self.0.lock() {
+            state.stats = TransferStats {
+                bytes: 0,
+                raw_bytes: 0,
+                chunks: 0,
+                expected_bytes: expected_bytes,
+            };
+            state.last_activity = Some(web_time::Instant::now());
+        }
+    }
+
+    pub fn add_chunk(&self, bytes: usize, raw_bytes: usize) {
+        if let Ok(mut state) = self.0.lock() {
+            state.stats.bytes += bytes;
+            state.stats.raw_bytes += raw_bytes;
+            state.stats.chunks += 1;
+            state.last_activity = Some(web_time::Instant::now());
+        }
+    }
+
+    fn touch(&self) {
+        if let Ok(mut state) = self.0.lock() {
+            state.last_activity = Some(web_time::Instant::now());
+        }
+    }
+
+    fn idle_time(&self) -> std::time::Duration {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|state| state.last_activity)
+            .map_or(std::time::Duration::ZERO, |t| t.elapsed())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn policy(timeout_ms: u64, retries: u32, backoff_ms: u64) -> RequestPolicy {
+        RequestPolicy {
+            timeout: std::time::Duration::from_millis(timeout_ms),
+            retries: retries,
+            backoff: std::time::Duration::from_millis(backoff_ms),
+        }
+    }
+
+    #[test]
+    fn transient_failures_are_retried_with_doubling_backoff() {
+        let attempts = AtomicU32::new(0);
+        let started = std::time::Instant::now();
+        let result = block_on(run(policy(1000, 3, 10), None, || {
+            let n = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n < 2 {
+                    Err(tonic::Status::unavailable("server restarting"))
+                } else {
+                    Ok(n)
+                }
+            }
+        }));
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        // 10 ms before the first retry, 20 ms before the second
+        assert!(started.elapsed() >= std::time::Duration::from_millis(30));
+    }
+
+    #[test]
+    fn permanent_failures_and_exhausted_retries_are_returned() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = block_on(run(policy(1000, 3, 1), None, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(tonic::Status::not_found("dataframe 3")) }
+        }));
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = block_on(run(policy(1000, 2, 1), None, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(tonic::Status::unavailable("down")) }
+        }));
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Unavailable);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn timeout_of_transfers_is_reset_by_every_chunk() {
+        let progress = TransferProgress::default();
+        // 100 ms in all, but never 50 ms without a chunk
+        let result = block_on(run(policy(50, 0, 0), Some(progress.clone()), || {
+            let progress = progress.clone();
+            async move {
+                progress.start(None);
+                for _ in 0..5 {
+                    sleep(std::time::Duration::from_millis(20)).await;
+                    progress.add_chunk(1, 1);
+                }
+                Ok(())
+            }
+        }));
+        assert!(result.is_ok());
+
+        let result: Result<(), _> = block_on(run(policy(50, 0, 0), None, || async {
+            sleep(std::time::Duration::from_millis(100)).await;
+            Ok(())
+        }));
+        assert_eq!(result.unwrap_err().code(), tonic::Code::DeadlineExceeded);
+    }
+
+    #[test]
+    fn stalled_transfer_is_not_started_over() {
+        let progress = TransferProgress::default();
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = block_on(run(policy(30, 2, 1), Some(progress.clone()), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            let progress = progress.clone();
+            async move {
+                progress.start(None);
+                progress.add_chunk(1, 1);
+                sleep(std::time::Duration::from_secs(10)).await;
+                Ok(())
+            }
+        }));
+        assert_eq!(result.unwrap_err().code(), tonic::Code::DeadlineExceeded);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn cancelled_request_resolves_to_cancelled() {
+        let (future, abort_handle) = cancellable(async {
+            sleep(std::time::Duration::from_secs(10)).await;
+            Ok(())
+        });
+        abort_handle.abort();
+        assert_eq!(block_on(future).unwrap_err().code(), tonic::Code::Cancelled);
+    }
+}
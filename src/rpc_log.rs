@@ -0,0 +1,182 @@
+//! Record of the rpcs sent to the backends, browsed in the network inspector pane.
+
+use crate::backend_talk::{grpc_data_transfer, grpc_fs, FetchedDataFrame};
+
+const MAX_RECORDS: usize = 2000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RpcOutcome {
+    Pending,
+    Ok,
+    Failed(tonic::Code, String),
+}
+
+#[derive(Clone, Debug)]
+pub struct RpcRecord {
+    pub server_address: String,
+    pub method: &'static str,
+    /// short summary of the request, e.g. the path listed or the dataframe id
+    pub args: String,
+    pub started_at: web_time::SystemTime,
+    /// until the response has arrived, including retries
+    pub latency: Option<std::time::Duration>,
+    /// received payload, if it could be measured
+    pub bytes: Option<usize>,
+    pub outcome: RpcOutcome,
+
+    started: web_time::Instant,
+    seq: u64,
+}
+
+impl RpcRecord {
+    /// Latency so far for pending calls.
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.latency.unwrap_or_else(|| self.started.elapsed())
+    }
+}
+
+#[derive(Default)]
+struct RpcLogState {
+    records: std::collections::VecDeque<RpcRecord>,
+    next_seq: u64,
+}
+
+/// Shared between the backends writing to it and the ui reading it. Only the latest
+/// `MAX_RECORDS` calls are kept.
+#[derive(Clone, Default)]
+pub struct RpcLog(std::sync::Arc<std::sync::Mutex<RpcLogState>>);
+
+impl RpcLog {
+    /// Record a call being sent, returns the handle to `finish` it with.
+    pub fn start(&self, server_address: &str, method: &'static str, args: String) -> u64 {
+        self.start_at(server_address, method, args, web_time::Instant::now())
+    }
+
+    /// Record a call sent at `started`, for polls which are only worth showing once answered.
+    pub fn start_at(
+        &self,
+        server_address: &str,
+        method: &'static str,
+        args: String,
+        started: web_time::Instant,
+    ) -> u64 {
+        let mut state = self.0.lock().unwrap();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.records.push_back(RpcRecord {
+            server_address: server_address.to_string(),
+            method: method,
+            args: args,
+            started_at: web_time::SystemTime::now() - started.elapsed(),
+            latency: None,
+            bytes: None,
+            outcome: RpcOutcome::Pending,
+            started: started,
+            seq: seq,
+        });
+        if state.records.len() > MAX_RECORDS {
+            state.records.pop_front();
+        }
+        seq
+    }
+
+    pub fn finish(&self, seq: u64, bytes: Option<usize>, result: Result<(), &tonic::Status>) {
+        let mut state = self.0.lock().unwrap();
+        // records are kept in seq order, so the position follows from the oldest one left
+        let front = match state.records.front() {
+            Some(front) => front.seq,
+            None => return,
+        };
+        if seq < front {
+            return;
+        }
+        if let Some(record) = state.records.get_mut((seq - front) as usize) {
+            record.latency = Some(record.started.elapsed());
+            record.bytes = bytes;
+            record.outcome = match result {
+                Ok(()) => RpcOutcome::Ok,
+                Err(status) => RpcOutcome::Failed(status.code(), status.message().to_string()),
+            };
+        }
+    }
+
+    pub fn records(&self) -> Vec<RpcRecord> {
+        self.0.lock().unwrap().records.iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().records.clear();
+    }
+}
+
+/// Size of a response as it came over the wire, for responses whose size is known afterwards.
+pub trait ResponseSize {
+    fn response_size(&self) -> Option<usize>;
+}
+
+macro_rules! encoded_response_size {
+    ($($message:ty),*) => {
+        $(
+            impl ResponseSize for $message {
+                fn response_size(&self) -> Option<usize> {
+                    Some(prost::Message::encoded_len(self))
+                }
+            }
+        )*
+    };
+}
+
+encoded_response_size!(
+    grpc_fs::Empty,
+    grpc_fs::PathMessage,
+    grpc_fs::ListResponse,
+    grpc_fs::DataFrameInfoList,
+    grpc_data_transfer::WorldMetadataList
+);
+
+macro_rules! unknown_response_size {
+    ($($response:ty),*) => {
+        $(
+            impl ResponseSize for $response {
+                fn response_size(&self) -> Option<usize> {
+                    None
+                }
+            }
+        )*
+    };
+}
+
+// streamed responses are measured chunk by chunk while they arrive, loading only returns an id
+unknown_response_size!(
+    usize,
+    polars::prelude::DataFrame,
+    FetchedDataFrame,
+    h_analyzer_data::WorldFrame,
+    Vec<h_analyzer_data::WorldFrame>
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_call_is_found_after_old_ones_are_dropped() {
+        let log = RpcLog::default();
+        for _ in 0..MAX_RECORDS {
+            let seq = log.start("fake://", "request_list", "/".to_string());
+            log.finish(seq, Some(4), Ok(()));
+        }
+        let seq = log.start("fake://", "get_df_request", "id 3".to_string());
+        log.finish(seq, None, Err(&tonic::Status::not_found("dataframe 3")));
+
+        let records = log.records();
+        assert_eq!(records.len(), MAX_RECORDS);
+        let last = records.last().unwrap();
+        assert_eq!(last.method, "get_df_request");
+        assert!(last.latency.is_some());
+        assert_eq!(
+            last.outcome,
+            RpcOutcome::Failed(tonic::Code::NotFound, "dataframe 3".to_string())
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! What was loaded on the backends, saved with the app state and loaded again on the next start.
+//! Dataframes get new ids when a server loads them again, so they are saved with their server and
+//! file, and the panes are pointed from the saved ids to the new ones once everything is back.
+
+use crate::backend_talk::grpc_fs;
+use crate::common_data::DataFrameKey;
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SessionDataFrame {
+    pub server_address: String,
+    /// id at the time of saving, which the panes of the saved layout refer to
+    pub df_id: DataFrameKey,
+    pub df_path: String,
+    pub load_option: grpc_fs::DataFrameLoadOption,
+}
+
+impl SessionDataFrame {
+    /// Whether `df_info` is this file, loaded with the same options.
+    pub fn is_loaded_as(&self, df_info: &grpc_fs::DataFrameInfo) -> bool {
+        // `updated` only tells whether the server reloaded the file since it was listed
+        let unflagged = |load_option: &grpc_fs::DataFrameLoadOption| grpc_fs::DataFrameLoadOption {
+            updated: false,
+            ..load_option.clone()
+        };
+        df_info.df_path == self.df_path
+            && df_info.load_option.as_ref().map(unflagged) == Some(unflagged(&self.load_option))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SessionRosbag {
+    pub server_address: String,
+    pub dirpath: String,
+    /// worlds which appeared on the server with the rosbag, empty in sessions saved before they were
+    #[serde(default)]
+    pub worlds: Vec<String>,
+}
+
+impl SessionRosbag {
+    pub fn is_same_rosbag(&self, other: &SessionRosbag) -> bool {
+        self.server_address == other.server_address && self.dirpath == other.dirpath
+    }
+
+    /// Whether the server still has the rosbag, judging by the worlds it lists.
+    pub fn is_listed_in(&self, worlds: &std::collections::HashSet<String>) -> bool {
+        !self.worlds.is_empty() && self.worlds.iter().all(|world| worlds.contains(world))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SessionWorld {
+    pub server_address: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Session {
+    pub dataframes: Vec<SessionDataFrame>,
+    pub rosbags: Vec<SessionRosbag>,
+    pub selected_world: Option<SessionWorld>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            dataframes: Vec::new(),
+            rosbags: Vec::new(),
+            selected_world: None,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Workspace files, the layout with its pane settings and the files the panes show, as json to be
+//! shared with others. Dataframes are referred to by server and file, like in a saved session,
+//! and loaded again on whatever backend the importing app is connected to.
+
+use crate::session;
+
+/// Incremented whenever a workspace file would be read wrongly by an older version.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Workspace<Layout> {
+    pub format_version: u32,
+    pub layout: Layout,
+    pub sources: session::Session,
+}
+
+impl<Layout: serde::Serialize> Workspace<Layout> {
+    pub fn new(layout: Layout, sources: session::Session) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            layout: layout,
+            sources: sources,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl<Layout: serde::de::DeserializeOwned> Workspace<Layout> {
+    pub fn from_json(contents: &[u8]) -> serde_json::Result<Self> {
+        let workspace: Self = serde_json::from_slice(contents)?;
+        if workspace.format_version > FORMAT_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "workspace format {} is newer than this app, which reads up to {}",
+                workspace.format_version, FORMAT_VERSION
+            )));
+        }
+        Ok(workspace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_data::DataFrameKey;
+
+    #[test]
+    fn workspace_is_read_back_from_json() {
+        let sources = session::Session {
+            dataframes: vec![session::SessionDataFrame {
+                server_address: "http://robot:50051".to_string(),
+                df_id: DataFrameKey::new(0, 3),
+                df_path: "/data/trj.csv".to_string(),
+                load_option: Default::default(),
+            }],
+            ..Default::default()
+        };
+        let json = Workspace::new(vec!["plot".to_string()], sources.clone())
+            .to_json()
+            .unwrap();
+        assert!(json.contains("/data/trj.csv"));
+
+        let workspace: Workspace<Vec<String>> = Workspace::from_json(json.as_bytes()).unwrap();
+        assert_eq!(workspace.layout, vec!["plot".to_string()]);
+        assert_eq!(workspace.sources, sources);
+
+        let newer = json.replace("\"format_version\": 1", "\"format_version\": 2");
+        assert!(Workspace::<Vec<String>>::from_json(newer.as_bytes()).is_err());
+    }
+}